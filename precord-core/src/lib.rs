@@ -1,10 +1,21 @@
+pub use sample::{ProcessCategory, ProcessSample};
+pub use sampler::{Sampler, SamplerResult};
+use std::time::Duration;
 pub use system::{Features, System};
 
 pub mod platform;
+mod sample;
+mod sampler;
 mod system;
+mod thread_counter;
 
 pub type Pid = u32;
 
+// Seconds that rates are taken over, updates in the same instant would divide by zero
+pub(crate) fn rate_secs(duration: Duration) -> f32 {
+    duration.as_secs_f32().max(0.001)
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Feature {0:?} missing")]
@@ -34,6 +45,9 @@ pub enum Error {
     #[cfg(target_os = "windows")]
     #[error("Etw error: {0:?}")]
     Etw(ferrisetw::trace::TraceError),
+    #[cfg(target_os = "macos")]
+    #[error("powermetrics: {0}")]
+    PowerMetrics(String),
     #[cfg(all(target_os = "macos", feature = "dtrace"))]
     #[error(transparent)]
     Dtrace(#[from] dtrace::Error),
@@ -41,14 +55,110 @@ pub enum Error {
     UnsupportedFeatures(Features),
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub enum GpuCalculation {
+    #[default]
     Max,
     Sum,
+    Average,
+}
+
+impl GpuCalculation {
+    // Combine readings of several engines or adapters, 0 when there is none
+    pub fn apply<I: IntoIterator<Item = f32>>(self, values: I) -> f32 {
+        let values = values.into_iter();
+        match self {
+            Self::Max => values.fold(0., f32::max),
+            Self::Sum => values.sum(),
+            Self::Average => {
+                let (sum, count) = values.fold((0., 0), |(sum, count), v| (sum + v, count + 1));
+                if count == 0 {
+                    0.
+                } else {
+                    sum / count as f32
+                }
+            }
+        }
+    }
+}
+
+// 100% of `System::process_cpu_usage` is one core with `PerCore`, all physical cores with `Normalized`
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum CpuScale {
+    #[default]
+    PerCore,
+    Normalized,
+}
+
+// Utilization of one graphics adapter
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuDevice {
+    pub name: String,
+    pub integrated: bool,
+    pub usage: f32,
+}
+
+// Throughput of one physical disk, in bytes per second
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskIo {
+    pub name: String,
+    pub read: f32,
+    pub write: f32,
+}
+
+// Frequency of one logical cpu in MHz, `group` is its cluster on Apple Silicon, e.g., E-Cluster,
+// or its package/NUMA node elsewhere
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuFrequency {
+    pub group: Option<String>,
+    pub frequency: f32,
+}
+
+// Cpu usage of one thread over the last update, 100% is one core
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreadUsage {
+    pub id: u64,
+    // Empty when the platform doesn't name threads, e.g., Windows
+    pub name: String,
+    pub cpu_usage: f32,
+}
+
+// Cpu time a thread used so far
+pub(crate) struct ThreadTime {
+    pub id: u64,
+    pub name: String,
+    pub cpu_time: Duration,
+}
+
+// Process found by `System::list_processes`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessBrief {
+    pub pid: Pid,
+    pub parent: Option<Pid>,
+    pub name: String,
+    pub cmd: Vec<String>,
+    pub status: ProcessStatus,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ProcessStatus {
+    Running,
+    Sleeping,
+    Stopped,
+    Zombie,
+    Other,
 }
 
-impl Default for GpuCalculation {
-    fn default() -> Self {
-        Self::Max
+impl From<sysinfo::ProcessStatus> for ProcessStatus {
+    fn from(status: sysinfo::ProcessStatus) -> Self {
+        match status {
+            sysinfo::ProcessStatus::Run => Self::Running,
+            sysinfo::ProcessStatus::Idle
+            | sysinfo::ProcessStatus::Sleep
+            | sysinfo::ProcessStatus::UninterruptibleDiskSleep => Self::Sleeping,
+            sysinfo::ProcessStatus::Stop | sysinfo::ProcessStatus::Tracing => Self::Stopped,
+            sysinfo::ProcessStatus::Zombie => Self::Zombie,
+            _ => Self::Other,
+        }
     }
 }
@@ -1,21 +1,978 @@
-use crate::{Error, Pid};
+use crate::{rate_secs, DiskIo, Error, Features, GpuCalculation, Pid, ThreadTime};
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub struct ThreadInfo;
+fn threads(pid: Pid) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(format!("/proc/{}/task", pid)) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_str()?.parse().ok())
+        .collect()
+}
+
+pub struct ThreadInfo {
+    pid: Pid,
+    id: u32,
+    name: String,
+    cpu_usage: f32,
+    last_cpu_times: u64,
+    last_global_cpu_times: u64,
+}
 
 impl ThreadInfo {
+    fn new(pid: Pid, tid: u32) -> Option<Self> {
+        let name = fs::read_to_string(format!("/proc/{}/task/{}/comm", pid, tid))
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_else(|_| "<Unnamed>".to_string());
+
+        Some(Self {
+            pid,
+            id: tid,
+            name,
+            cpu_usage: 0.,
+            last_cpu_times: thread_cpu_times(pid, tid)?,
+            last_global_cpu_times: global_cpu_times()?,
+        })
+    }
+
     pub fn id(&self) -> u64 {
-        unimplemented!()
+        self.id as _
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
     pub fn cpu_usage(&self) -> f32 {
-        unimplemented!()
+        self.cpu_usage
+    }
+
+    fn refresh_cpu_usage(&mut self, nb_cpus: u32) {
+        let (Some(cpu_times), Some(global_cpu_times)) =
+            (thread_cpu_times(self.pid, self.id), global_cpu_times())
+        else {
+            return;
+        };
+
+        let delta = cpu_times.saturating_sub(self.last_cpu_times);
+        let g_delta = global_cpu_times.saturating_sub(self.last_global_cpu_times);
+
+        if g_delta > 0 {
+            self.cpu_usage = 100. * delta as f32 / g_delta as f32 * nb_cpus as f32;
+        }
+        self.last_cpu_times = cpu_times;
+        self.last_global_cpu_times = global_cpu_times;
+    }
+}
+
+// utime + stime in jiffies
+fn thread_cpu_times(pid: Pid, tid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/task/{}/stat", pid, tid)).ok()?;
+    // Skip `pid (comm)`, comm may contain spaces and parentheses
+    let mut fields = stat.get(stat.rfind(')')? + 1..)?.split_whitespace();
+    // Fields after comm start from `state`(3), utime(14) and stime(15)
+    let utime: u64 = fields.nth(11)?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+// Sum of all cpu time in jiffies
+fn global_cpu_times() -> Option<u64> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let line = stat.lines().next()?;
+    let mut fields = line.split_whitespace();
+    if fields.next()? != "cpu" {
+        return None;
+    }
+    // guest and guest_nice are already included in user and nice
+    Some(fields.take(8).filter_map(|f| f.parse::<u64>().ok()).sum())
+}
+
+pub fn threads_info(pid: Pid, nb_cpus: u32) -> Result<Vec<ThreadInfo>, Error> {
+    let mut threads_info: Vec<_> = threads(pid)
+        .into_iter()
+        .filter_map(|tid| ThreadInfo::new(pid, tid))
+        .collect();
+
+    thread::sleep(Duration::from_secs(1));
+
+    for t in threads_info.iter_mut() {
+        t.refresh_cpu_usage(nb_cpus);
+    }
+    Ok(threads_info)
+}
+
+// Without sleeping, usage comes from the difference between two calls
+pub(crate) fn thread_times(pid: Pid) -> Option<Vec<ThreadTime>> {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks <= 0 {
+        return None;
+    }
+    // None once the process is gone
+    fs::read_dir(format!("/proc/{}/task", pid)).ok()?;
+
+    Some(
+        threads(pid)
+            .into_iter()
+            .filter_map(|tid| {
+                let jiffies = thread_cpu_times(pid, tid)?;
+                let name = fs::read_to_string(format!("/proc/{}/task/{}/comm", pid, tid))
+                    .map(|s| s.trim_end().to_string())
+                    .unwrap_or_default();
+                Some(ThreadTime {
+                    id: tid as _,
+                    name,
+                    cpu_time: Duration::from_secs_f64(jiffies as f64 / ticks as f64),
+                })
+            })
+            .collect(),
+    )
+}
+
+// NUMA node of a logical cpu, from its `node<N>` link
+pub fn cpu_numa_node(cpu: usize) -> Option<u32> {
+    fs::read_dir(format!("/sys/devices/system/cpu/cpu{}", cpu))
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find_map(|e| e.file_name().to_str()?.strip_prefix("node")?.parse().ok())
+}
+
+pub fn threads_count(pid: Pid) -> Option<u32> {
+    let entries = fs::read_dir(format!("/proc/{}/task", pid)).ok()?;
+    Some(entries.count() as u32)
+}
+
+// Open file descriptors, None for processes of other users
+pub fn proc_fds(pid: Pid) -> Option<u32> {
+    let entries = fs::read_dir(format!("/proc/{}/fd", pid)).ok()?;
+    Some(entries.count() as u32)
+}
+
+// Descriptors linked to `socket:[inode]`
+pub fn proc_sockets(pid: Pid) -> Option<u32> {
+    let entries = fs::read_dir(format!("/proc/{}/fd", pid)).ok()?;
+    Some(
+        entries
+            .filter_map(|e| fs::read_link(e.ok()?.path()).ok())
+            .filter(|target| target.to_str().is_some_and(|t| t.starts_with("socket:")))
+            .count() as u32,
+    )
+}
+
+// Leader of the session of `pid`, or the main process of its systemd service for processes
+// leading their own session, e.g., daemons reparented to init
+pub fn proc_responsible(pid: Pid) -> Option<Pid> {
+    // Fields after comm start from `state`(3), session(6)
+    let session: Pid = proc_stat_field(pid, 3)?.parse().ok()?;
+    if session != 0 && session != pid && Path::new(&format!("/proc/{}", session)).exists() {
+        return Some(session);
+    }
+
+    // The oldest process of the service stands for its main pid
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    let path = cgroup.lines().find_map(|line| line.strip_prefix("0::"))?;
+    if !path.ends_with(".service") {
+        return None;
     }
+    cgroup_pids(Path::new(path.trim_start_matches('/')))?
+        .into_iter()
+        .filter_map(|p| {
+            // starttime(22)
+            let start_time: u64 = proc_stat_field(p, 19)?.parse().ok()?;
+            Some((start_time, p))
+        })
+        .min()
+        .map(|(_, p)| p)
+        .filter(|&p| p != pid)
+}
+
+// The `nth` field after comm of /proc/<pid>/stat
+fn proc_stat_field(pid: Pid, nth: usize) -> Option<String> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    Some(
+        stat.get(stat.rfind(')')? + 1..)?
+            .split_whitespace()
+            .nth(nth)?
+            .to_string(),
+    )
 }
 
-pub fn threads_info(_pid: Pid, _nb_cpus: u32) -> Result<Vec<ThreadInfo>, Error> {
-    Ok(vec![])
+// Processes of a cgroup directory, e.g., /sys/fs/cgroup/system.slice/docker-<id>.scope,
+// paths relative to /sys/fs/cgroup are accepted too
+pub fn cgroup_pids(path: &Path) -> Option<Vec<Pid>> {
+    let dir = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        Path::new("/sys/fs/cgroup").join(path)
+    };
+    let content = fs::read_to_string(dir.join("cgroup.procs")).ok()?;
+    Some(
+        content
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect(),
+    )
 }
 
-pub fn threads_count(_pid: Pid) -> Option<u32> {
-    None
+pub struct ProcCounter {
+    process_counters: Vec<ProcessProcCounter>,
+    // Clock ticks per second of `/proc/<pid>/stat`
+    clock_ticks: u64,
+    // Block I/O delays are only accounted when enabled, always on before kernel 5.14
+    delay_accounting: bool,
+}
+
+impl ProcCounter {
+    pub fn new<T: IntoIterator<Item = Pid>>(pids: T) -> Self {
+        let clock_ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        let mut counter = Self {
+            process_counters: vec![],
+            clock_ticks: if clock_ticks > 0 {
+                clock_ticks as _
+            } else {
+                100
+            },
+            delay_accounting: fs::read_to_string("/proc/sys/kernel/task_delayacct")
+                .map_or(true, |v| v.trim() != "0"),
+        };
+        counter.add_pids(pids);
+        counter
+    }
+
+    pub fn add_pids<T: IntoIterator<Item = Pid>>(&mut self, pids: T) {
+        for pid in pids {
+            if self.process_counters.iter().any(|p| p.pid == pid) {
+                continue;
+            }
+
+            self.process_counters.push(ProcessProcCounter {
+                pid,
+                last_io: ProcIo::read(pid),
+                last_major_faults: proc_major_faults(pid),
+                last_ctx_switches: proc_ctx_switches(pid),
+                last_blkio_ticks: proc_blkio_ticks(pid),
+                read_bytes: 0,
+                write_bytes: 0,
+                read_ops: 0,
+                write_ops: 0,
+                major_faults: 0,
+                ctx_switches: 0,
+                blkio_ticks: 0,
+            });
+        }
+    }
+
+    pub fn remove_pids(&mut self, pids: &[Pid]) {
+        self.process_counters.retain(|p| !pids.contains(&p.pid));
+    }
+
+    pub fn update(&mut self) {
+        // Sources are dropped once they can't be read, e.g., `io` without ptrace access keeps
+        // stat/status ones going
+        for p in self.process_counters.iter_mut() {
+            if let Some(last) = p.last_io.take() {
+                p.last_io = ProcIo::read(p.pid);
+                if let Some(io) = &p.last_io {
+                    p.read_bytes = io.read_bytes.saturating_sub(last.read_bytes);
+                    p.write_bytes = io.write_bytes.saturating_sub(last.write_bytes);
+                    p.read_ops = io.syscr.saturating_sub(last.syscr);
+                    p.write_ops = io.syscw.saturating_sub(last.syscw);
+                }
+            }
+
+            if let Some(last) = p.last_major_faults.take() {
+                p.last_major_faults = proc_major_faults(p.pid);
+                p.major_faults = p.last_major_faults.map_or(0, |v| v.saturating_sub(last));
+            }
+
+            if let Some(last) = p.last_ctx_switches.take() {
+                p.last_ctx_switches = proc_ctx_switches(p.pid);
+                p.ctx_switches = p.last_ctx_switches.map_or(0, |v| v.saturating_sub(last));
+            }
+
+            if let Some(last) = p.last_blkio_ticks.take() {
+                p.last_blkio_ticks = proc_blkio_ticks(p.pid);
+                p.blkio_ticks = p.last_blkio_ticks.map_or(0, |v| v.saturating_sub(last));
+            }
+        }
+    }
+
+    pub fn process_read_bytes(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.last_io.is_some())
+            .map(|p| p.read_bytes)
+    }
+
+    pub fn process_write_bytes(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.last_io.is_some())
+            .map(|p| p.write_bytes)
+    }
+
+    // Read/write syscalls, files and sockets alike
+    pub fn process_read_ops(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.last_io.is_some())
+            .map(|p| p.read_ops)
+    }
+
+    pub fn process_write_ops(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.last_io.is_some())
+            .map(|p| p.write_ops)
+    }
+
+    pub fn process_page_faults(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.last_major_faults.is_some())
+            .map(|p| p.major_faults)
+    }
+
+    pub fn process_ctx_switches(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.last_ctx_switches.is_some())
+            .map(|p| p.ctx_switches)
+    }
+
+    // Time blocked on I/O since the last update, None without delay accounting
+    pub fn process_io_wait(&self, pid: Pid) -> Option<Duration> {
+        if !self.delay_accounting {
+            return None;
+        }
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.last_blkio_ticks.is_some())
+            .map(|p| Duration::from_secs_f64(p.blkio_ticks as f64 / self.clock_ticks as f64))
+    }
+}
+
+struct ProcessProcCounter {
+    pid: Pid,
+    // None once the source can't be read
+    last_io: Option<ProcIo>,
+    last_major_faults: Option<u64>,
+    last_ctx_switches: Option<u64>,
+    last_blkio_ticks: Option<u64>,
+    read_bytes: u64,
+    write_bytes: u64,
+    read_ops: u64,
+    write_ops: u64,
+    major_faults: u64,
+    ctx_switches: u64,
+    blkio_ticks: u64,
+}
+
+#[derive(Default)]
+struct ProcIo {
+    read_bytes: u64,
+    write_bytes: u64,
+    syscr: u64,
+    syscw: u64,
+}
+
+impl ProcIo {
+    // Reading `io` of other users' processes requires ptrace access
+    fn read(pid: Pid) -> Option<Self> {
+        let content = fs::read_to_string(format!("/proc/{}/io", pid)).ok()?;
+        let mut io = Self::default();
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            match key {
+                "read_bytes" => io.read_bytes = value.trim().parse().ok()?,
+                "write_bytes" => io.write_bytes = value.trim().parse().ok()?,
+                "syscr" => io.syscr = value.trim().parse().ok()?,
+                "syscw" => io.syscw = value.trim().parse().ok()?,
+                _ => {}
+            }
+        }
+        Some(io)
+    }
+}
+
+fn proc_major_faults(pid: Pid) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Fields after comm start from `state`(3), majflt(12)
+    stat.get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .nth(9)?
+        .parse()
+        .ok()
+}
+
+fn proc_blkio_ticks(pid: Pid) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Fields after comm start from `state`(3), delayacct_blkio_ticks(42)
+    stat.get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .nth(39)?
+        .parse()
+        .ok()
+}
+
+fn proc_ctx_switches(pid: Pid) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let mut ctx_switches = None;
+    for line in status.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if key == "voluntary_ctxt_switches" || key == "nonvoluntary_ctxt_switches" {
+            *ctx_switches.get_or_insert(0) += value.trim().parse::<u64>().ok()?;
+        }
+    }
+    ctx_switches
+}
+
+const HWMON_CPU_DRIVERS: [&str; 2] = ["coretemp", "k10temp"];
+
+pub fn hwmon_cpu_temperature() -> Vec<f32> {
+    let mut cpus_temp = vec![];
+
+    let Ok(dirs) = fs::read_dir("/sys/class/hwmon") else {
+        return cpus_temp;
+    };
+
+    let mut hwmons: Vec<_> = dirs.filter_map(|d| d.ok()).map(|d| d.path()).collect();
+    hwmons.sort();
+
+    for hwmon in hwmons {
+        match fs::read_to_string(hwmon.join("name")) {
+            Ok(name) if HWMON_CPU_DRIVERS.contains(&name.trim()) => {}
+            _ => continue,
+        }
+
+        let Ok(entries) = fs::read_dir(&hwmon) else {
+            continue;
+        };
+
+        // temp{N}_input, sorted by N so the rows keep a stable order
+        let mut inputs: Vec<(u32, _)> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let file_name = e.file_name();
+                let index = file_name
+                    .to_str()?
+                    .strip_prefix("temp")?
+                    .strip_suffix("_input")?
+                    .parse()
+                    .ok()?;
+                Some((index, e.path()))
+            })
+            .collect();
+        inputs.sort_by_key(|(index, _)| *index);
+
+        for (_, input) in inputs {
+            if let Some(millidegree) = fs::read_to_string(input)
+                .ok()
+                .and_then(|s| s.trim().parse::<i64>().ok())
+            {
+                cpus_temp.push(millidegree as f32 / 1000.);
+            }
+        }
+    }
+
+    cpus_temp
+}
+
+// State of charge of the first battery, in percent
+pub fn battery_percent() -> Option<f32> {
+    let mut supplies: Vec<_> = fs::read_dir("/sys/class/power_supply")
+        .ok()?
+        .filter_map(|d| d.ok())
+        .map(|d| d.path())
+        .collect();
+    supplies.sort();
+
+    supplies
+        .into_iter()
+        .filter(|supply| {
+            fs::read_to_string(supply.join("type")).is_ok_and(|t| t.trim() == "Battery")
+        })
+        .find_map(|supply| read_u64(supply.join("capacity")))
+        .map(|capacity| capacity as f32)
+}
+
+// Package energy from RAPL, or the amd_energy hwmon when RAPL isn't present
+pub struct EnergyMeter {
+    counters: Vec<EnergyFile>,
+    delta_uj: u64,
+}
+
+impl EnergyMeter {
+    pub fn new() -> Option<Self> {
+        let mut counters = rapl_energy_files();
+        if counters.is_empty() {
+            counters = amd_energy_files();
+        }
+
+        // energy_uj is only readable by root on recent kernels
+        for counter in counters.iter_mut() {
+            counter.last_uj = Some(read_u64(&counter.path)?);
+        }
+
+        (!counters.is_empty()).then_some(Self {
+            counters,
+            delta_uj: 0,
+        })
+    }
+
+    pub fn update(&mut self) {
+        self.delta_uj = 0;
+        for counter in self.counters.iter_mut() {
+            let Some(uj) = read_u64(&counter.path) else {
+                continue;
+            };
+            if let Some(last_uj) = counter.last_uj {
+                self.delta_uj += if uj >= last_uj {
+                    uj - last_uj
+                } else {
+                    // Wrapped around at max_energy_range_uj
+                    counter.max_range_uj.saturating_sub(last_uj) + uj
+                };
+            }
+            counter.last_uj = Some(uj);
+        }
+    }
+
+    // Average watts over the last update
+    pub fn power(&self, duration: Duration) -> f32 {
+        self.delta_uj as f32 / 1_000_000. / rate_secs(duration)
+    }
+}
+
+struct EnergyFile {
+    path: PathBuf,
+    max_range_uj: u64,
+    last_uj: Option<u64>,
+}
+
+// Bytes read and written by every physical disk, listed once when created
+pub struct DiskCounter {
+    disks: Vec<String>,
+    last: HashMap<String, (u64, u64)>,
+    delta: Vec<(u64, u64)>,
+}
+
+impl DiskCounter {
+    pub fn new() -> Option<Self> {
+        let stats = diskstats()?;
+        // Block devices backed by hardware, loop/ram/dm-/md devices have no `device`
+        let mut disks: Vec<String> = fs::read_dir("/sys/block")
+            .ok()?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().join("device").exists())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|name| stats.contains_key(name))
+            .collect();
+        disks.sort();
+
+        Some(Self {
+            delta: vec![(0, 0); disks.len()],
+            disks,
+            last: stats,
+        })
+    }
+
+    pub fn update(&mut self) {
+        let Some(stats) = diskstats() else {
+            return;
+        };
+        for (disk, delta) in self.disks.iter().zip(self.delta.iter_mut()) {
+            *delta = match (stats.get(disk), self.last.get(disk)) {
+                (Some(&(read, write)), Some(&(last_read, last_write))) => (
+                    read.saturating_sub(last_read),
+                    write.saturating_sub(last_write),
+                ),
+                _ => (0, 0),
+            };
+        }
+        self.last = stats;
+    }
+
+    // Bytes per second over the last update
+    pub fn disks(&self, duration: Duration) -> Vec<DiskIo> {
+        let secs = rate_secs(duration);
+        self.disks
+            .iter()
+            .zip(self.delta.iter())
+            .map(|(name, &(read, write))| DiskIo {
+                name: name.clone(),
+                read: read as f32 / secs,
+                write: write as f32 / secs,
+            })
+            .collect()
+    }
+}
+
+// Bytes read and written by every block device, sectors are always 512 bytes there
+fn diskstats() -> Option<HashMap<String, (u64, u64)>> {
+    let content = fs::read_to_string("/proc/diskstats").ok()?;
+    Some(
+        content
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<_> = line.split_whitespace().collect();
+                let read: u64 = fields.get(5)?.parse().ok()?;
+                let write: u64 = fields.get(9)?.parse().ok()?;
+                Some((fields[2].to_string(), (read * 512, write * 512)))
+            })
+            .collect(),
+    )
+}
+
+fn read_u64<P: AsRef<Path>>(path: P) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+// Top-level zones (intel-rapl:N) only, subzones are already included in their package
+fn rapl_energy_files() -> Vec<EnergyFile> {
+    let Ok(entries) = fs::read_dir("/sys/class/powercap") else {
+        return vec![];
+    };
+
+    let mut zones: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("intel-rapl:"))
+                .is_some_and(|index| !index.contains(':'))
+        })
+        .map(|e| e.path())
+        .collect();
+    zones.sort();
+
+    zones
+        .into_iter()
+        .filter(|zone| zone.join("energy_uj").exists())
+        .map(|zone| EnergyFile {
+            path: zone.join("energy_uj"),
+            max_range_uj: read_u64(zone.join("max_energy_range_uj")).unwrap_or(u64::MAX),
+            last_uj: None,
+        })
+        .collect()
+}
+
+// Socket counters (label Esocket{N}) of the amd_energy driver
+fn amd_energy_files() -> Vec<EnergyFile> {
+    let Ok(dirs) = fs::read_dir("/sys/class/hwmon") else {
+        return vec![];
+    };
+
+    let mut files = vec![];
+    for hwmon in dirs.filter_map(|d| d.ok()).map(|d| d.path()) {
+        match fs::read_to_string(hwmon.join("name")) {
+            Ok(name) if name.trim() == "amd_energy" => {}
+            _ => continue,
+        }
+
+        let Ok(entries) = fs::read_dir(&hwmon) else {
+            continue;
+        };
+        for e in entries.filter_map(|e| e.ok()) {
+            let file_name = e.file_name();
+            let Some(prefix) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix("_label"))
+            else {
+                continue;
+            };
+            match fs::read_to_string(e.path()) {
+                Ok(label) if label.trim().starts_with("Esocket") => {}
+                _ => continue,
+            }
+            files.push(EnergyFile {
+                path: hwmon.join(format!("{}_input", prefix)),
+                max_range_uj: u64::MAX,
+                last_uj: None,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+const TRACEFS_ROOTS: [&str; 2] = ["/sys/kernel/tracing", "/sys/kernel/debug/tracing"];
+// Queued by whoever flips the page, i.e., the compositor unless the process owns the
+// display (fullscreen unredirected or KMS), so readings are an approximation
+const FRAME_EVENT: &str = "events/drm/drm_vblank_event_queued/enable";
+
+// Counts vblank events per pid in a private tracefs instance, needs root
+pub struct FrameTracer {
+    instance: PathBuf,
+    pipe: Option<File>,
+    pending: String,
+    frames: HashMap<Pid, u32>,
+    last_update: Instant,
+    fps: HashMap<Pid, f32>,
+}
+
+impl FrameTracer {
+    // Tracefs is mounted with the drm event and instances can be created in it
+    pub fn available() -> bool {
+        TRACEFS_ROOTS.iter().map(Path::new).any(|root| {
+            let Ok(instances) = CString::new(root.join("instances").as_os_str().as_bytes()) else {
+                return false;
+            };
+            root.join(FRAME_EVENT).exists()
+                && unsafe { libc::access(instances.as_ptr(), libc::W_OK) } == 0
+        })
+    }
+
+    pub fn new() -> Result<Self, Error> {
+        let root = TRACEFS_ROOTS
+            .iter()
+            .map(Path::new)
+            .find(|root| root.join("instances").is_dir())
+            .ok_or(Error::UnsupportedFeatures(Features::FPS))?;
+
+        let instance = root
+            .join("instances")
+            .join(format!("precord-{}", std::process::id()));
+        fs::create_dir(&instance).map_err(|err| match err.kind() {
+            io::ErrorKind::PermissionDenied => Error::AccessDenied,
+            _ => Error::UnsupportedFeatures(Features::FPS),
+        })?;
+
+        // The instance is removed by Drop if anything below fails
+        let mut tracer = Self {
+            instance,
+            pipe: None,
+            pending: String::new(),
+            frames: HashMap::new(),
+            last_update: Instant::now(),
+            fps: HashMap::new(),
+        };
+
+        fs::write(tracer.instance.join(FRAME_EVENT), "1")
+            .map_err(|_| Error::UnsupportedFeatures(Features::FPS))?;
+        tracer.pipe = Some(
+            File::options()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(tracer.instance.join("trace_pipe"))
+                .map_err(|_| Error::UnsupportedFeatures(Features::FPS))?,
+        );
+
+        Ok(tracer)
+    }
+
+    pub fn update(&mut self) {
+        let Some(pipe) = &mut self.pipe else {
+            return;
+        };
+
+        let mut buf = [0; 64 * 1024];
+        loop {
+            match pipe.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => self.pending.push_str(&String::from_utf8_lossy(&buf[..n])),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+
+        // Keep the trailing partial line for the next read
+        let complete = self.pending.rfind('\n').map_or(0, |i| i + 1);
+        for line in self.pending[..complete].lines() {
+            if let Some(pid) = trace_line_pid(line) {
+                *self.frames.entry(pid).or_default() += 1;
+            }
+        }
+        self.pending.drain(..complete);
+
+        let now = Instant::now();
+        let secs = rate_secs(now - self.last_update);
+        self.last_update = now;
+        self.fps = self
+            .frames
+            .drain()
+            .map(|(pid, frames)| (pid, frames as f32 / secs))
+            .collect();
+    }
+
+    pub fn fps(&self, pid: Pid) -> f32 {
+        self.fps.get(&pid).copied().unwrap_or(0.)
+    }
+}
+
+impl Drop for FrameTracer {
+    fn drop(&mut self) {
+        let _ = fs::write(self.instance.join(FRAME_EVENT), "0");
+        // tracefs refuses to remove an instance with open files
+        self.pipe.take();
+        let _ = fs::remove_dir(&self.instance);
+    }
+}
+
+// `<comm>-<pid> [<cpu>] <flags> <timestamp>: <event>: ...`, comm may contain '-' or spaces
+fn trace_line_pid(line: &str) -> Option<Pid> {
+    if line.starts_with('#') {
+        return None;
+    }
+    let (task, _) = line.split_once(" [")?;
+    let (_, pid) = task.trim().rsplit_once('-')?;
+    pid.parse().ok()
+}
+
+pub struct DrmCounter {
+    process_counters: Vec<ProcessDrmCounter>,
+}
+
+impl DrmCounter {
+    pub fn new<T: IntoIterator<Item = Pid>>(pids: T) -> Self {
+        let mut counter = Self {
+            process_counters: vec![],
+        };
+        counter.add_pids(pids);
+        counter
+    }
+
+    pub fn add_pids<T: IntoIterator<Item = Pid>>(&mut self, pids: T) {
+        for pid in pids {
+            if self.process_counters.iter().any(|p| p.pid == pid) {
+                continue;
+            }
+
+            self.process_counters.push(ProcessDrmCounter {
+                pid,
+                last_engines: drm_engines(pid).unwrap_or_default(),
+                busy_ns: Default::default(),
+            });
+        }
+    }
+
+    pub fn remove_pids(&mut self, pids: &[Pid]) {
+        self.process_counters.retain(|p| !pids.contains(&p.pid));
+    }
+
+    pub fn update(&mut self) {
+        for p in self.process_counters.iter_mut() {
+            let engines = drm_engines(p.pid).unwrap_or_default();
+            p.busy_ns = engines
+                .iter()
+                .map(|(engine, &ns)| {
+                    let last = p.last_engines.get(engine).copied().unwrap_or(ns);
+                    (engine.clone(), ns.saturating_sub(last))
+                })
+                .collect();
+            p.last_engines = engines;
+        }
+    }
+
+    pub fn process_gpu_usage(
+        &self,
+        pid: Pid,
+        calc: GpuCalculation,
+        duration: Duration,
+    ) -> Option<f32> {
+        let p = self.process_counters.iter().find(|p| p.pid == pid)?;
+        let duration_ns = rate_secs(duration) * 1e9;
+        Some(calc.apply(p.busy_ns.values().map(|&ns| 100. * ns as f32 / duration_ns)))
+    }
+}
+
+struct ProcessDrmCounter {
+    pid: Pid,
+    last_engines: HashMap<String, u64>,
+    busy_ns: HashMap<String, u64>,
+}
+
+// https://docs.kernel.org/gpu/drm-usage-stats.html
+fn drm_engines(pid: Pid) -> Option<HashMap<String, u64>> {
+    let entries = fs::read_dir(format!("/proc/{}/fdinfo", pid)).ok()?;
+
+    let mut clients = HashSet::new();
+    let mut engines = HashMap::new();
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+
+        let mut client_id = None;
+        let mut client_engines = vec![];
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            if key == "drm-client-id" {
+                client_id = Some(value.trim().to_string());
+            } else if let Some(engine) = key.strip_prefix("drm-engine-") {
+                if let Some(ns) = value
+                    .trim()
+                    .strip_suffix("ns")
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                {
+                    client_engines.push((engine.to_string(), ns));
+                }
+            }
+        }
+
+        // Duplicated fds share the same client
+        let Some(client_id) = client_id else {
+            continue;
+        };
+        if !clients.insert(client_id) {
+            continue;
+        }
+
+        for (engine, ns) in client_engines {
+            *engines.entry(engine).or_default() += ns;
+        }
+    }
+
+    Some(engines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_line() {
+        assert_eq!(
+            trace_line_pid(
+                "          <idle>-0       [002] d..2.  1234.567890: sched_switch: prev_comm=swapper"
+            ),
+            Some(0)
+        );
+        assert_eq!(
+            trace_line_pid(
+                " kworker/u16:2-crypto-2345  [000] ....  10.000001: drm_vblank_event: crtc=0 seq=1"
+            ),
+            Some(2345)
+        );
+        assert_eq!(
+            trace_line_pid("  Web Content-4321  [001] ...1  55.5: dma_fence_signaled: driver=i915"),
+            Some(4321)
+        );
+    }
+
+    #[test]
+    fn trace_malformed_line() {
+        assert_eq!(trace_line_pid("# tracer: nop"), None);
+        assert_eq!(trace_line_pid("#           TASK-PID     CPU#"), None);
+        assert_eq!(trace_line_pid("Xorg-abc [000] .... 1.0: event"), None);
+        assert_eq!(trace_line_pid("no brackets here"), None);
+        assert_eq!(trace_line_pid(""), None);
+    }
 }
@@ -1,16 +1,21 @@
-use crate::{Error, Pid};
-use core_foundation::base::{kCFAllocatorDefault, CFRelease, ToVoid};
+use crate::{rate_secs, CpuFrequency, DiskIo, Error, GpuCalculation, GpuDevice, Pid, ThreadTime};
+use core_foundation::base::{
+    kCFAllocatorDefault, CFGetTypeID, CFRelease, CFTypeRef, TCFType, ToVoid,
+};
+use core_foundation::data::{CFData, CFDataGetTypeID, CFDataRef};
 use core_foundation::dictionary::{CFDictionaryGetValueIfPresent, CFMutableDictionaryRef};
-use core_foundation::number::{kCFNumberCharType, CFNumberGetValue, CFNumberRef};
-use core_foundation::string::CFString;
+use core_foundation::number::{
+    kCFNumberCharType, kCFNumberSInt64Type, CFNumberGetValue, CFNumberRef,
+};
+use core_foundation::string::{CFString, CFStringGetTypeID, CFStringRef};
 use mach2::{kern_return, mach_types, task, task_info, traps};
 use serde::Deserialize;
 use std::ffi::c_void;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
 use std::process::Command;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{mpsc, Once};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{mem, process, ptr, thread};
 pub use types::MachPort;
 use IOKit_sys::*;
@@ -29,13 +34,12 @@ mod types;
 
 #[derive(Debug, Default, Deserialize)]
 struct PowerMetricsResult {
-    #[allow(dead_code)]
+    #[serde(default)]
     tasks: Vec<Task>,
     processor: ProcessorInfo,
 }
 
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
 pub struct Task {
     // Maybe negative
     pid: i32,
@@ -45,7 +49,12 @@ pub struct Task {
 
 pub struct CommandSource {
     last_update: Instant,
+    pids: Vec<Pid>,
+    net_traffic: bool,
+    frame_rate: bool,
+    top: bool,
     power_metrics_result: PowerMetricsResult,
+    power_metrics_rx: Option<Receiver<Result<PowerMetricsResult, Error>>>,
     process_command_result: Vec<ProcessCommandResult>,
     process_command_rx: Receiver<ProcessCommandResult>,
 }
@@ -53,10 +62,16 @@ pub struct CommandSource {
 impl CommandSource {
     pub fn new<T: IntoIterator<Item = Pid> + Clone>(
         pids: T,
+        power_metrics: bool,
+        interval: Duration,
         net_traffic: bool,
         frame_rate: bool,
         top: bool,
-    ) -> Self {
+    ) -> Result<Self, Error> {
+        // powermetrics refuses to run without root, its categories stay empty then and are left out
+        // of `System::supported_features`
+        let power_metrics = power_metrics && unsafe { libc::geteuid() } == 0;
+
         let pids: Vec<_> = pids.into_iter().collect();
 
         let process_command_result: Vec<_> = pids
@@ -68,10 +83,43 @@ impl CommandSource {
             })
             .collect();
 
+        // Power metrics
+        let power_metrics_rx = if power_metrics {
+            let (tx, rx) = mpsc::channel();
+            let power_metrics_runner = PowerMetricsRunner::new(tx, interval);
+            thread::spawn(move || power_metrics_runner.run());
+            Some(rx)
+        } else {
+            None
+        };
+
+        let mut command_source = Self {
+            last_update: Instant::now(),
+            pids,
+            net_traffic,
+            frame_rate,
+            top,
+            power_metrics_result: Default::default(),
+            power_metrics_rx,
+            process_command_result,
+            process_command_rx: mpsc::channel().1,
+        };
+        command_source.spawn_runners();
+        Ok(command_source)
+    }
+
+    // Runners stop once they fail to send to a replaced receiver
+    fn spawn_runners(&mut self) {
         let (tx, rx) = mpsc::channel();
+        self.process_command_rx = rx;
+
+        let pids = self.pids.clone();
+        if pids.is_empty() {
+            return;
+        }
 
         // Net traffic
-        if net_traffic && !pids.is_empty() {
+        if self.net_traffic {
             let net_top_runner = NetTopRunner::new(tx.clone());
             let pids = pids.clone();
             thread::spawn(move || {
@@ -80,7 +128,7 @@ impl CommandSource {
         };
 
         // Frame rate
-        if frame_rate && !pids.is_empty() {
+        if self.frame_rate {
             #[cfg(feature = "dtrace")]
             match FrameRateRunner::new(tx.clone(), pids.clone()) {
                 Ok(Some(frame_rate)) => {
@@ -91,41 +139,59 @@ impl CommandSource {
         };
 
         // Top
-        if top && !pids.is_empty() {
+        if self.top {
             let top_runner = top::TopRunner::new(tx);
             thread::spawn(move || top_runner.run(pids));
         }
+    }
 
-        Self {
-            last_update: Instant::now(),
-            power_metrics_result: Default::default(),
-            process_command_result,
-            process_command_rx: rx,
+    pub fn add_pids<T: IntoIterator<Item = Pid>>(&mut self, pids: T) {
+        let mut changed = false;
+        for pid in pids {
+            if self.pids.contains(&pid) {
+                continue;
+            }
+
+            self.pids.push(pid);
+            self.process_command_result.push(ProcessCommandResult {
+                pid,
+                ..Default::default()
+            });
+            changed = true;
+        }
+
+        if changed {
+            self.spawn_runners();
         }
     }
 
-    pub fn update_power_metrics_data(&mut self) {
-        let o = Command::new("powermetrics")
-            .args([
-                "--samplers",
-                "tasks,cpu_power",
-                "--show-process-gpu",
-                "-n1",
-                "-i1000",
-                "-f",
-                "plist",
-            ])
-            .output()
-            .unwrap();
+    pub fn remove_pids(&mut self, pids: &[Pid]) {
+        let len = self.pids.len();
+        self.pids.retain(|pid| !pids.contains(pid));
+        self.process_command_result
+            .retain(|p| !pids.contains(&p.pid));
 
-        match o.status.code() {
-            Some(0) => {}
-            _ => {
-                panic!("Error: {}", String::from_utf8_lossy(&o.stderr));
-            }
+        if self.pids.len() != len {
+            self.spawn_runners();
         }
+    }
 
-        self.power_metrics_result = plist::from_bytes(o.stdout.as_slice()).unwrap();
+    // The previous sample is kept on failure
+    pub fn update_power_metrics_data(&mut self) -> Result<(), Error> {
+        let mut r = Ok(());
+        if let Some(rx) = &self.power_metrics_rx {
+            // Keep the latest streamed sample
+            while let Ok(result) = rx.try_recv() {
+                match result {
+                    Ok(result) => {
+                        self.power_metrics_result = result;
+                        r = Ok(());
+                    }
+                    Err(err) => r = Err(err),
+                }
+            }
+        }
+        r
     }
 
     pub fn update(&mut self) {
@@ -142,7 +208,7 @@ impl CommandSource {
             }
         }
         let now = Instant::now();
-        let d = (now - self.last_update).as_secs_f32();
+        let d = rate_secs(now - self.last_update);
         for p in self.process_command_result.iter_mut() {
             p.bytes_in_per_sec = (p.bytes_in as f32 / d) as _;
             p.bytes_out_per_sec = (p.bytes_out as f32 / d) as _;
@@ -155,15 +221,19 @@ impl CommandSource {
         self.last_update = now;
     }
 
-    pub fn cpu_frequency(&self) -> Vec<f32> {
+    pub fn cpu_frequency(&self) -> Vec<CpuFrequency> {
         if !self.power_metrics_result.processor.clusters.is_empty() {
             // Apple Silicon
             self.power_metrics_result
                 .processor
                 .clusters
                 .iter()
-                .flat_map(|c| c.cpus.iter())
-                .map(|c| c.freq_hz / 1_000_000.0)
+                .flat_map(|cluster| {
+                    cluster.cpus.iter().map(|c| CpuFrequency {
+                        group: Some(cluster.name.clone()).filter(|name| !name.is_empty()),
+                        frequency: c.freq_hz / 1_000_000.0,
+                    })
+                })
                 .collect()
         } else {
             // Intel
@@ -171,13 +241,28 @@ impl CommandSource {
                 .processor
                 .packages
                 .iter()
-                .flat_map(|p| p.cores.iter())
-                .flat_map(|c| c.cpus.iter())
-                .map(|c| c.freq_hz / 1_000_000.0)
+                .enumerate()
+                .flat_map(|(i, p)| {
+                    p.cores
+                        .iter()
+                        .flat_map(|c| c.cpus.iter())
+                        .map(move |c| CpuFrequency {
+                            group: Some(format!("Package{}", i)),
+                            frequency: c.freq_hz / 1_000_000.0,
+                        })
+                })
                 .collect()
         }
     }
 
+    pub fn process_gpu_usage(&self, pid: Pid) -> Option<f32> {
+        self.power_metrics_result
+            .tasks
+            .iter()
+            .find(|t| t.pid == pid as i32)
+            .map(|t| t.gputime_ms_per_s / 10.)
+    }
+
     pub fn ane_power(&self) -> f32 {
         self.power_metrics_result.processor.ane_power
     }
@@ -229,6 +314,9 @@ struct Cpu {
 // Apple Silicon
 #[derive(Debug, Deserialize)]
 struct Cluster {
+    // e.g., E-Cluster, P0-Cluster
+    #[serde(default)]
+    name: String,
     cpus: Vec<Cpu>,
 }
 
@@ -307,8 +395,12 @@ impl IOKitRegistry {
                                     std::mem::transmute(&mut device_utilization),
                                 ) {
                                     self.last_result.push(IOKitResult {
-                                        // TODO: Get accelerator name
-                                        io_class: "".to_string(),
+                                        io_class: dictionary_string(props, "IOClass")
+                                            .unwrap_or_default(),
+                                        // Apple Silicon names the accelerator itself, discrete
+                                        // and Intel adapters carry it on the IOPCIDevice parent
+                                        model: dictionary_string(props, "model")
+                                            .or_else(|| parent_model(entry)),
                                         performance_statistics: PerformanceStatistics {
                                             device_utilization: device_utilization as _,
                                         },
@@ -328,20 +420,235 @@ impl IOKitRegistry {
         }
     }
 
-    pub fn sys_gpu_usage(&self) -> f32 {
-        let mut max: f32 = 0.0;
-        for r in &self.last_result {
-            max = max.max(r.performance_statistics.device_utilization);
+    pub fn sys_gpu_usage(&self, calc: GpuCalculation) -> f32 {
+        calc.apply(
+            self.last_result
+                .iter()
+                .map(|r| r.performance_statistics.device_utilization),
+        )
+    }
+
+    pub fn gpu_devices(&self) -> Vec<GpuDevice> {
+        self.last_result
+            .iter()
+            .map(|r| GpuDevice {
+                name: r.model.clone().unwrap_or_else(|| r.io_class.clone()),
+                // Apple Silicon (AGX) and Intel graphics share the system memory
+                integrated: r.io_class.starts_with("AGX")
+                    || r.io_class.starts_with("Intel")
+                    || r.model.as_deref().is_some_and(|m| m.starts_with("Apple")),
+                usage: r.performance_statistics.device_utilization,
+            })
+            .collect()
+    }
+}
+
+// String value of `key`, `model` of IOPCIDevice is a NUL-terminated CFData
+unsafe fn dictionary_string(dict: CFMutableDictionaryRef, key: &str) -> Option<String> {
+    let mut value: CFTypeRef = ptr::null();
+    if CFDictionaryGetValueIfPresent(dict, CFString::new(key).to_void(), &mut value) == 0 {
+        return None;
+    }
+
+    let type_id = CFGetTypeID(value);
+    if type_id == CFStringGetTypeID() {
+        Some(CFString::wrap_under_get_rule(value as CFStringRef).to_string())
+    } else if type_id == CFDataGetTypeID() {
+        let data = CFData::wrap_under_get_rule(value as CFDataRef);
+        Some(
+            String::from_utf8_lossy(data.bytes())
+                .trim_end_matches('\0')
+                .to_string(),
+        )
+    } else {
+        None
+    }
+}
+
+unsafe fn parent_model(entry: io_registry_entry_t) -> Option<String> {
+    let mut parent: io_registry_entry_t = 0;
+    if IORegistryEntryGetParentEntry(entry, c"IOService".as_ptr(), &mut parent) != kIOReturnSuccess
+    {
+        return None;
+    }
+
+    let mut model = None;
+    let mut props: CFMutableDictionaryRef = ptr::null_mut();
+    if IORegistryEntryCreateCFProperties(
+        parent,
+        &mut props as *mut CFMutableDictionaryRef as *mut _,
+        kCFAllocatorDefault as _,
+        0,
+    ) == kIOReturnSuccess
+    {
+        model = dictionary_string(props, "model");
+        CFRelease(props.to_void());
+    }
+    IOObjectRelease(parent);
+    model
+}
+
+// Bytes read and written by every physical disk, listed once when created
+pub struct DiskCounter {
+    disks: Vec<String>,
+    last: Vec<(String, u64, u64)>,
+    delta: Vec<(u64, u64)>,
+}
+
+impl Default for DiskCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiskCounter {
+    pub fn new() -> Self {
+        let last = block_storage_statistics();
+        let mut disks: Vec<_> = last.iter().map(|(name, _, _)| name.clone()).collect();
+        disks.sort();
+        Self {
+            delta: vec![(0, 0); disks.len()],
+            disks,
+            last,
+        }
+    }
+
+    pub fn update(&mut self) {
+        let stats = block_storage_statistics();
+        for (disk, delta) in self.disks.iter().zip(self.delta.iter_mut()) {
+            let find = |stats: &[(String, u64, u64)]| {
+                stats
+                    .iter()
+                    .find(|(name, _, _)| name == disk)
+                    .map(|&(_, read, write)| (read, write))
+            };
+            *delta = match (find(&stats), find(&self.last)) {
+                (Some((read, write)), Some((last_read, last_write))) => (
+                    read.saturating_sub(last_read),
+                    write.saturating_sub(last_write),
+                ),
+                _ => (0, 0),
+            };
+        }
+        self.last = stats;
+    }
+
+    // Bytes per second over the last update
+    pub fn disks(&self, duration: Duration) -> Vec<DiskIo> {
+        let secs = rate_secs(duration);
+        self.disks
+            .iter()
+            .zip(self.delta.iter())
+            .map(|(name, &(read, write))| DiskIo {
+                name: name.clone(),
+                read: read as f32 / secs,
+                write: write as f32 / secs,
+            })
+            .collect()
+    }
+}
+
+// Cumulative bytes of every IOBlockStorageDriver, named by the BSD name of its IOMedia, e.g., disk0
+fn block_storage_statistics() -> Vec<(String, u64, u64)> {
+    let mut stats = vec![];
+
+    unsafe {
+        let matching = IOServiceMatching(c"IOBlockStorageDriver".as_ptr());
+        let mut it: io_iterator_t = 0;
+        // The matching dictionary is consumed by the call
+        if IOServiceGetMatchingServices(kIOMasterPortDefault, matching, &mut it) != kIOReturnSuccess
+        {
+            return stats;
+        }
+
+        loop {
+            let entry = IOIteratorNext(it);
+            if entry == 0 {
+                break;
+            }
+
+            let mut props: CFMutableDictionaryRef = ptr::null_mut();
+            if IORegistryEntryCreateCFProperties(
+                entry,
+                &mut props as *mut CFMutableDictionaryRef as *mut _,
+                kCFAllocatorDefault as _,
+                0,
+            ) == kIOReturnSuccess
+            {
+                let mut statistics: CFMutableDictionaryRef = ptr::null_mut();
+                if CFDictionaryGetValueIfPresent(
+                    props,
+                    CFString::new("Statistics").to_void(),
+                    &mut statistics as *mut CFMutableDictionaryRef as *mut _,
+                ) != 0
+                {
+                    if let Some(name) = child_bsd_name(entry) {
+                        stats.push((
+                            name,
+                            dictionary_u64(statistics, "Bytes (Read)").unwrap_or(0),
+                            dictionary_u64(statistics, "Bytes (Write)").unwrap_or(0),
+                        ));
+                    }
+                }
+                CFRelease(props.to_void());
+            }
+
+            IOObjectRelease(entry);
         }
-        max
+        IOObjectRelease(it);
     }
+
+    stats
+}
+
+unsafe fn dictionary_u64(dict: CFMutableDictionaryRef, key: &str) -> Option<u64> {
+    let mut number: CFNumberRef = ptr::null();
+    if CFDictionaryGetValueIfPresent(
+        dict,
+        CFString::new(key).to_void(),
+        &mut number as *mut CFNumberRef as *mut _,
+    ) == 0
+    {
+        return None;
+    }
+
+    let mut value: i64 = 0;
+    CFNumberGetValue(
+        number,
+        kCFNumberSInt64Type,
+        &mut value as *mut i64 as *mut c_void,
+    )
+    .then_some(value as u64)
+}
+
+unsafe fn child_bsd_name(entry: io_registry_entry_t) -> Option<String> {
+    let mut child: io_registry_entry_t = 0;
+    if IORegistryEntryGetChildEntry(entry, c"IOService".as_ptr(), &mut child) != kIOReturnSuccess {
+        return None;
+    }
+
+    let mut name = None;
+    let mut props: CFMutableDictionaryRef = ptr::null_mut();
+    if IORegistryEntryCreateCFProperties(
+        child,
+        &mut props as *mut CFMutableDictionaryRef as *mut _,
+        kCFAllocatorDefault as _,
+        0,
+    ) == kIOReturnSuccess
+    {
+        name = dictionary_string(props, "BSD Name");
+        CFRelease(props.to_void());
+    }
+    IOObjectRelease(child);
+    name
 }
 
 #[derive(Debug, Deserialize)]
 struct IOKitResult {
-    #[allow(dead_code)]
     #[serde(rename = "IOClass")]
     io_class: String,
+    #[serde(default)]
+    model: Option<String>,
     #[serde(rename = "PerformanceStatistics")]
     performance_statistics: PerformanceStatistics,
 }
@@ -364,6 +671,86 @@ pub struct ProcessCommandResult {
     mach_ports: u32,
 }
 
+struct PowerMetricsRunner {
+    tx: Sender<Result<PowerMetricsResult, Error>>,
+    // Samples are streamed at the sampling interval, every update sees a fresh one
+    interval: Duration,
+}
+
+impl PowerMetricsRunner {
+    fn new(tx: Sender<Result<PowerMetricsResult, Error>>, interval: Duration) -> Self {
+        Self { tx, interval }
+    }
+
+    fn run(self) {
+        let mut child = match Command::new("powermetrics")
+            .args([
+                "--samplers",
+                "tasks,cpu_power",
+                "--show-process-gpu",
+                "-i",
+                // 0 would turn periodic samples off
+                self.interval.as_millis().max(1).to_string().as_str(),
+                "-f",
+                "plist",
+            ])
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) => {
+                let _ = self.tx.send(Err(Error::PowerMetrics(err.to_string())));
+                return;
+            }
+        };
+
+        // Samples are plist documents separated by NUL
+        let mut buf = BufReader::new(child.stdout.as_mut().unwrap());
+        let mut sample = vec![];
+        while let Ok(read) = buf.read_until(b'\0', &mut sample) {
+            if read == 0 {
+                break;
+            }
+
+            let data = sample
+                .strip_suffix(b"\0")
+                .unwrap_or(sample.as_slice())
+                .trim_ascii();
+            if data.is_empty() {
+                sample.clear();
+                continue;
+            }
+
+            // A malformed sample is skipped
+            let result =
+                plist::from_bytes(data).map_err(|err| Error::PowerMetrics(err.to_string()));
+
+            if self.tx.send(result).is_err() {
+                break;
+            }
+
+            sample.clear();
+        }
+
+        let _ = child.kill();
+        let mut stderr = String::new();
+        if let Some(mut e) = child.stderr.take() {
+            let _ = e.read_to_string(&mut stderr);
+        }
+        if let Ok(status) = child.wait() {
+            if !status.success() && !stderr.trim().is_empty() {
+                let err = if stderr.contains("superuser") || stderr.contains("root") {
+                    Error::AccessDenied
+                } else {
+                    Error::PowerMetrics(stderr.trim().to_string())
+                };
+                let _ = self.tx.send(Err(err));
+            }
+        }
+    }
+}
+
 struct NetTopRunner {
     tx: Sender<ProcessCommandResult>,
 }
@@ -418,21 +805,10 @@ impl NetTopRunner {
                 continue;
             }
 
-            let data: Vec<_> = line.split(',').collect();
-            if data.len() < 3 {
+            let Some((pid, bytes_in, bytes_out)) = parse_nettop_line(&line) else {
                 line.clear();
                 continue;
-            }
-
-            let process: Vec<_> = data[0].split('.').collect();
-            if process.len() < 2 {
-                line.clear();
-                continue;
-            }
-
-            let pid: Pid = process.last().unwrap().parse().unwrap();
-            let bytes_in: u32 = data[1].parse().unwrap();
-            let bytes_out: u32 = data[2].parse().unwrap();
+            };
 
             if let Err(_) = self.tx.send(ProcessCommandResult {
                 pid,
@@ -450,6 +826,22 @@ impl NetTopRunner {
     }
 }
 
+// e.g., `Google Chrome H.1234,5678,910,`, names may contain dots, the pid follows the last one
+fn parse_nettop_line(line: &str) -> Option<(Pid, u32, u32)> {
+    let mut data = line.trim_end().split(',');
+    let (_, pid) = data.next()?.rsplit_once('.')?;
+    let pid = pid.parse().ok()?;
+    let bytes_in = data.next()?.parse().ok()?;
+    let bytes_out = data.next()?.parse().ok()?;
+    Some((pid, bytes_in, bytes_out))
+}
+
+// Output of the `printf("%d",pid)` action
+#[cfg(any(feature = "dtrace", test))]
+fn parse_frame_pid(output: &str) -> Option<Pid> {
+    output.trim().parse().ok()
+}
+
 #[cfg(feature = "dtrace")]
 struct FrameRateRunner {
     tx: Sender<ProcessCommandResult>,
@@ -521,7 +913,9 @@ impl FrameRateRunner {
         let mut cont = true;
         while cont {
             let r = self.dt.work(|output| {
-                let pid: Pid = output.parse().unwrap();
+                let Some(pid) = parse_frame_pid(output) else {
+                    return;
+                };
 
                 if let Err(_) = self.tx.send(ProcessCommandResult {
                     pid,
@@ -555,6 +949,54 @@ pub fn get_pid_responsible() -> Option<extern "C" fn(libc::pid_t) -> libc::pid_t
     }
 }
 
+// Pids of a running app by its name or bundle id, e.g., Safari or com.apple.Safari, helpers it's
+// responsible for included
+pub fn app_pids(app: &str) -> Option<Vec<Pid>> {
+    let output = Command::new("lsappinfo")
+        .args(["info", "-only", "pid", app])
+        .output()
+        .ok()?;
+    let pid = parse_lsappinfo_pid(&String::from_utf8_lossy(&output.stdout))?;
+
+    let mut pids = vec![pid];
+    if let Some(get_pid_responsible) = get_pid_responsible() {
+        pids.extend(
+            all_pids()
+                .into_iter()
+                .filter(|&p| p as Pid != pid && get_pid_responsible(p) as Pid == pid)
+                .map(|p| p as Pid),
+        );
+    }
+    Some(pids)
+}
+
+// e.g., `"pid"=1234`, nothing is printed for apps that aren't running
+fn parse_lsappinfo_pid(output: &str) -> Option<Pid> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("\"pid\"="))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn all_pids() -> Vec<libc::pid_t> {
+    unsafe {
+        let n = libc::proc_listallpids(ptr::null_mut(), 0);
+        if n <= 0 {
+            return vec![];
+        }
+        // Room for processes started in between
+        let mut pids: Vec<libc::pid_t> = vec![0; n as usize + 64];
+        let n = libc::proc_listallpids(
+            pids.as_mut_ptr() as _,
+            (pids.len() * mem::size_of::<libc::pid_t>()) as _,
+        );
+        pids.truncate(n.max(0) as usize);
+        pids
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize)]
 struct Entitlements {
@@ -631,6 +1073,20 @@ fn csr_allow_unrestricted_dtrace() -> bool {
     config & CSR_ALLOW_UNRESTRICTED_DTRACE == CSR_ALLOW_UNRESTRICTED_DTRACE
 }
 
+// Frames are traced by dtrace, which needs root and SIP allowing it for processes without get-task-allow
+pub fn frame_rate_available() -> bool {
+    #[cfg(feature = "dtrace")]
+    {
+        let root = unsafe { libc::geteuid() == 0 };
+        root && csr_allow_unrestricted_dtrace()
+    }
+
+    #[cfg(not(feature = "dtrace"))]
+    {
+        false
+    }
+}
+
 const PROC_PIDLISTFDS: libc::c_int = 1;
 const PROC_PIDTHREADINFO: libc::c_int = 5;
 const PROC_PIDLISTTHREADS: libc::c_int = 6;
@@ -642,32 +1098,51 @@ struct proc_fd_info {
     pub proc_fdtype: u32,
 }
 
-pub fn proc_fds(pid: Pid) -> Option<u32> {
-    let mut buf: Vec<u8> = Vec::with_capacity(64 * mem::size_of::<proc_fd_info>());
+const PROX_FDTYPE_SOCKET: u32 = 2;
+
+fn list_fds(pid: Pid) -> Option<Vec<proc_fd_info>> {
+    let mut buf: Vec<proc_fd_info> = Vec::with_capacity(64);
 
     loop {
+        let buf_size = buf.capacity() * mem::size_of::<proc_fd_info>();
         let actual_buf_size = unsafe {
             libc::proc_pidinfo(
                 pid as _,
                 PROC_PIDLISTFDS,
                 0,
                 buf.as_mut_ptr() as _,
-                buf.capacity() as _,
+                buf_size as _,
             )
         };
         if actual_buf_size < 0 {
             return None;
         }
 
-        if actual_buf_size as usize >= buf.capacity() {
+        if actual_buf_size as usize >= buf_size {
             buf.reserve(buf.capacity() * 2);
             continue;
         }
 
-        break Some(actual_buf_size as u32 / mem::size_of::<proc_fd_info>() as u32);
+        unsafe {
+            buf.set_len(actual_buf_size as usize / mem::size_of::<proc_fd_info>());
+        }
+        break Some(buf);
     }
 }
 
+pub fn proc_fds(pid: Pid) -> Option<u32> {
+    Some(list_fds(pid)?.len() as _)
+}
+
+pub fn proc_sockets(pid: Pid) -> Option<u32> {
+    Some(
+        list_fds(pid)?
+            .iter()
+            .filter(|fd| fd.proc_fdtype == PROX_FDTYPE_SOCKET)
+            .count() as _,
+    )
+}
+
 // https://opensource.apple.com/source/dtrace/dtrace-370.40.1/lib/libproc/libproc.c.auto.html
 #[cfg(feature = "dtrace")]
 unsafe fn proc_is_translated(pid: Pid) -> bool {
@@ -810,6 +1285,63 @@ fn threads_info_privilege(pid: Pid) -> Result<Vec<types::ThreadInfo>, Error> {
     }
 }
 
+// Without sleeping, usage comes from the difference between two calls. Thread ids of
+// PROC_PIDLISTTHREADS are stable over the life of a thread
+pub(crate) fn thread_times(pid: Pid) -> Option<Vec<ThreadTime>> {
+    let mut buf: Vec<u64> = Vec::with_capacity(16);
+
+    unsafe {
+        loop {
+            let actual_buf_size = libc::proc_pidinfo(
+                pid as _,
+                PROC_PIDLISTTHREADS,
+                0,
+                buf.as_mut_ptr() as _,
+                8 * buf.capacity() as libc::c_int,
+            );
+            if actual_buf_size <= 0 {
+                return None;
+            }
+
+            if actual_buf_size as usize >= 8 * buf.capacity() {
+                buf.reserve(buf.capacity() * 2);
+                continue;
+            }
+
+            buf.set_len(actual_buf_size as usize / 8);
+            break;
+        }
+
+        Some(
+            buf.into_iter()
+                .filter_map(|tid| {
+                    let mut ti: types::proc_threadinfo = mem::zeroed();
+                    if libc::proc_pidinfo(
+                        pid as _,
+                        PROC_PIDTHREADINFO,
+                        tid,
+                        (&mut ti) as *mut types::proc_threadinfo as _,
+                        mem::size_of::<types::proc_threadinfo>() as _,
+                    ) != mem::size_of::<types::proc_threadinfo>() as _
+                    {
+                        return None;
+                    }
+
+                    let name = std::ffi::CStr::from_ptr(ti.pth_name.as_ptr() as _)
+                        .to_string_lossy()
+                        .into_owned();
+                    // Run times are in nanoseconds
+                    Some(ThreadTime {
+                        id: tid,
+                        name,
+                        cpu_time: Duration::from_nanos(ti.pth_user_time + ti.pth_system_time),
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
 pub fn threads_count(pid: Pid) -> Option<u32> {
     let mut buf: Vec<u64> = Vec::with_capacity(16);
 
@@ -854,6 +1386,135 @@ extern "C" {
 #[link(name = "sysmon")]
 extern "C" {}
 
+// Memory tagged as graphics (Metal buffers, IOSurfaces) in the task ledger
+pub fn graphics_footprint(pid: Pid) -> Option<u64> {
+    unsafe {
+        let mut task = 0;
+        let r = traps::task_for_pid(traps::mach_task_self(), pid as _, &mut task);
+        if r != kern_return::KERN_SUCCESS {
+            return None;
+        }
+        let task = types::MachPort::from_raw(task);
+
+        let mut info: types::task_vm_info = mem::zeroed();
+        let mut count = (mem::size_of::<types::task_vm_info>() / mem::size_of::<libc::natural_t>())
+            as libc::mach_msg_type_number_t;
+        let r = task::task_info(
+            task.as_raw(),
+            task_info::TASK_VM_INFO,
+            (&mut info) as *mut types::task_vm_info as _,
+            &mut count,
+        );
+        // Older kernels fill fewer fields
+        let required = (mem::offset_of!(types::task_vm_info, ledger_tag_graphics_nofootprint)
+            / mem::size_of::<libc::natural_t>())
+            as libc::mach_msg_type_number_t;
+        if r != kern_return::KERN_SUCCESS || count < required {
+            return None;
+        }
+
+        Some(
+            (info.ledger_tag_graphics_footprint + info.ledger_tag_graphics_footprint_compressed)
+                .max(0) as u64,
+        )
+    }
+}
+
+pub struct EnergyCounter {
+    process_counters: Vec<ProcessEnergyCounter>,
+}
+
+impl EnergyCounter {
+    pub fn new<T: IntoIterator<Item = Pid>>(pids: T) -> Self {
+        let mut counter = Self {
+            process_counters: vec![],
+        };
+        counter.add_pids(pids);
+        counter
+    }
+
+    pub fn add_pids<T: IntoIterator<Item = Pid>>(&mut self, pids: T) {
+        for pid in pids {
+            if self.process_counters.iter().any(|p| p.pid == pid) {
+                continue;
+            }
+
+            let task = unsafe {
+                let mut task = 0;
+                let r = traps::task_for_pid(traps::mach_task_self(), pid as _, &mut task);
+                if r == kern_return::KERN_SUCCESS {
+                    Some(MachPort::from_raw(task))
+                } else {
+                    None
+                }
+            };
+            let last_energy = task.as_ref().and_then(task_energy_nj);
+            self.process_counters.push(ProcessEnergyCounter {
+                pid,
+                task,
+                last_energy,
+                energy: 0,
+            });
+        }
+    }
+
+    pub fn remove_pids(&mut self, pids: &[Pid]) {
+        self.process_counters.retain(|p| !pids.contains(&p.pid));
+    }
+
+    pub fn update(&mut self) {
+        for p in self.process_counters.iter_mut() {
+            let energy = p.task.as_ref().and_then(task_energy_nj);
+            p.energy = match (p.last_energy, energy) {
+                (Some(last), Some(energy)) => energy.saturating_sub(last),
+                _ => 0,
+            };
+            p.last_energy = energy;
+        }
+    }
+
+    // mW
+    pub fn process_power(&self, pid: Pid, duration: Duration) -> Option<f32> {
+        let p = self.process_counters.iter().find(|p| p.pid == pid)?;
+        p.last_energy?;
+        Some(p.energy as f32 / rate_secs(duration) / 1_000_000.)
+    }
+}
+
+struct ProcessEnergyCounter {
+    pid: Pid,
+    task: Option<MachPort>,
+    // nJ
+    last_energy: Option<u64>,
+    energy: u64,
+}
+
+// Energy billed to the task in nJ, only reported on Apple Silicon
+fn task_energy_nj(target: &MachPort) -> Option<u64> {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        let mut info: types::task_power_info_v2 = mem::zeroed();
+        let mut size = mem::size_of::<types::task_power_info_v2>() as u32;
+        let r = task::task_info(
+            target.as_raw(),
+            task_info::TASK_POWER_INFO_V2,
+            (&mut info) as *mut types::task_power_info_v2 as _,
+            &mut size,
+        );
+        if r != kern_return::KERN_SUCCESS {
+            None
+        } else {
+            Some(info.task_energy)
+        }
+    }
+
+    #[cfg(not(target_arch = "aarch64"))]
+    {
+        let _ = target;
+        None
+    }
+}
+
 pub fn gpu_time_ns(target: &MachPort) -> u64 {
     unsafe {
         let mut info: types::task_power_info_v2 = mem::zeroed();
@@ -871,3 +1532,52 @@ pub fn gpu_time_ns(target: &MachPort) -> u64 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nettop_line() {
+        assert_eq!(
+            parse_nettop_line("launchd.1,1024,2048,\r\n"),
+            Some((1, 1024, 2048))
+        );
+        assert_eq!(
+            parse_nettop_line("Google Chrome H.1234,5,6\n"),
+            Some((1234, 5, 6))
+        );
+        assert_eq!(
+            parse_nettop_line("com.apple.WebKit.Networking.987,0,0,"),
+            Some((987, 0, 0))
+        );
+    }
+
+    #[test]
+    fn nettop_malformed_line() {
+        assert_eq!(parse_nettop_line(",bytes_in,bytes_out,"), None);
+        assert_eq!(
+            parse_nettop_line("script: nettop: No such file or directory"),
+            None
+        );
+        assert_eq!(parse_nettop_line("launchd.1,1024"), None);
+        assert_eq!(parse_nettop_line("launchd.x,1024,2048,"), None);
+        assert_eq!(parse_nettop_line("launchd.1,-1,2048,"), None);
+        assert_eq!(parse_nettop_line(""), None);
+    }
+
+    #[test]
+    fn lsappinfo_pid() {
+        assert_eq!(parse_lsappinfo_pid("\"pid\"=1234\n"), Some(1234));
+        assert_eq!(parse_lsappinfo_pid(""), None);
+        assert_eq!(parse_lsappinfo_pid("\"pid\"=[ NULL ]\n"), None);
+    }
+
+    #[test]
+    fn frame_pid() {
+        assert_eq!(parse_frame_pid("1234"), Some(1234));
+        assert_eq!(parse_frame_pid("1234\n"), Some(1234));
+        assert_eq!(parse_frame_pid(""), None);
+        assert_eq!(parse_frame_pid("dtrace: error"), None);
+    }
+}
@@ -83,3 +83,26 @@ impl TopProcess {
         Some(Self { pid, mach_ports })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let p = TopProcess::parse("1234  58\n").unwrap();
+        assert_eq!((p.pid, p.mach_ports), (1234, 58));
+        // Ports are suffixed by + or - when they changed since the last sample
+        let p = TopProcess::parse("1234  60+ \r\n").unwrap();
+        assert_eq!((p.pid, p.mach_ports), (1234, 60));
+    }
+
+    #[test]
+    fn parse_malformed() {
+        assert!(TopProcess::parse("PID    #PORTS").is_none());
+        assert!(TopProcess::parse("Processes: 512 total, 3 running").is_none());
+        assert!(TopProcess::parse("1234").is_none());
+        assert!(TopProcess::parse("1234  +").is_none());
+        assert!(TopProcess::parse("").is_none());
+    }
+}
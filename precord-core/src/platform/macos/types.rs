@@ -107,3 +107,52 @@ pub struct task_power_info_v2 {
 pub struct pm_task_energy_data_t {
     pub data: [u8; 408],
 }
+
+// `task_vm_info` up to rev3, which adds the ledger tags
+#[allow(non_camel_case_types)]
+#[repr(C, packed(4))]
+pub struct task_vm_info {
+    pub virtual_size: u64,
+    pub region_count: i32,
+    pub page_size: i32,
+    pub resident_size: u64,
+    pub resident_size_peak: u64,
+    pub device: u64,
+    pub device_peak: u64,
+    pub internal: u64,
+    pub internal_peak: u64,
+    pub external: u64,
+    pub external_peak: u64,
+    pub reusable: u64,
+    pub reusable_peak: u64,
+    pub purgeable_volatile_pmap: u64,
+    pub purgeable_volatile_resident: u64,
+    pub purgeable_volatile_virtual: u64,
+    pub compressed: u64,
+    pub compressed_peak: u64,
+    pub compressed_lifetime: u64,
+    pub phys_footprint: u64,
+    pub min_address: u64,
+    pub max_address: u64,
+    pub ledger_phys_footprint_peak: i64,
+    pub ledger_purgeable_nonvolatile: i64,
+    pub ledger_purgeable_novolatile_compressed: i64,
+    pub ledger_purgeable_volatile: i64,
+    pub ledger_purgeable_volatile_compressed: i64,
+    pub ledger_tag_network_nonvolatile: i64,
+    pub ledger_tag_network_nonvolatile_compressed: i64,
+    pub ledger_tag_network_volatile: i64,
+    pub ledger_tag_network_volatile_compressed: i64,
+    pub ledger_tag_media_footprint: i64,
+    pub ledger_tag_media_footprint_compressed: i64,
+    pub ledger_tag_media_nofootprint: i64,
+    pub ledger_tag_media_nofootprint_compressed: i64,
+    pub ledger_tag_graphics_footprint: i64,
+    pub ledger_tag_graphics_footprint_compressed: i64,
+    pub ledger_tag_graphics_nofootprint: i64,
+    pub ledger_tag_graphics_nofootprint_compressed: i64,
+    pub ledger_tag_neural_footprint: i64,
+    pub ledger_tag_neural_footprint_compressed: i64,
+    pub ledger_tag_neural_nofootprint: i64,
+    pub ledger_tag_neural_nofootprint_compressed: i64,
+}
@@ -12,6 +12,13 @@ pub use linux::threads_count;
 #[cfg(target_os = "macos")]
 pub use macos::threads_count;
 
+#[cfg(target_os = "windows")]
+pub(crate) use self::windows::thread_times;
+#[cfg(target_os = "linux")]
+pub(crate) use linux::thread_times;
+#[cfg(target_os = "macos")]
+pub(crate) use macos::thread_times;
+
 #[cfg(target_os = "linux")]
 pub mod linux;
 #[cfg(target_os = "macos")]
@@ -11,6 +11,7 @@ pub struct Battery {
     handle: OwnedHandle,
     bws: Power::BATTERY_WAIT_STATUS,
     relative: bool,
+    full_charged_capacity: u32,
 }
 
 impl Battery {
@@ -130,6 +131,7 @@ impl Battery {
                 handle: h_battery,
                 bws,
                 relative: bi.Capabilities & Power::BATTERY_CAPACITY_RELATIVE > 0,
+                full_charged_capacity: bi.FullChargedCapacity,
             }))
         }
     }
@@ -138,31 +140,44 @@ impl Battery {
         let mut rate = 0.;
 
         if !self.relative {
-            unsafe {
-                let mut bs: Power::BATTERY_STATUS = mem::zeroed();
-                if !IO::DeviceIoControl(
-                    super::windows_raw_handle(self.handle.as_raw_handle()),
-                    Power::IOCTL_BATTERY_QUERY_STATUS,
-                    Some(&self.bws as *const Power::BATTERY_WAIT_STATUS as _),
-                    mem::size_of_val(&self.bws) as _,
-                    Some(&mut bs as *mut Power::BATTERY_STATUS as _),
-                    mem::size_of::<Power::BATTERY_STATUS>() as _,
-                    None,
-                    None,
-                )
-                .as_bool()
-                {
-                    return Err(Error::WinError(windows::core::Error::from_win32()));
-                }
-
-                if bs.Rate != Power::BATTERY_UNKNOWN_RATE as i32 {
-                    rate = -bs.Rate.min(0) as f32 / 1000.;
-                }
+            let bs = self.status()?;
+            if bs.Rate != Power::BATTERY_UNKNOWN_RATE as i32 {
+                rate = -bs.Rate.min(0) as f32 / 1000.;
             }
         }
 
         Ok(rate)
     }
+
+    // Remaining capacity of the full charged one, relative batteries report both in the same unit
+    pub fn percent(&self) -> Result<f32, Error> {
+        let bs = self.status()?;
+        if self.full_charged_capacity == 0 || bs.Capacity == Power::BATTERY_UNKNOWN_CAPACITY {
+            return Ok(0.);
+        }
+        Ok((bs.Capacity as f32 / self.full_charged_capacity as f32 * 100.).min(100.))
+    }
+
+    fn status(&self) -> Result<Power::BATTERY_STATUS, Error> {
+        unsafe {
+            let mut bs: Power::BATTERY_STATUS = mem::zeroed();
+            if !IO::DeviceIoControl(
+                super::windows_raw_handle(self.handle.as_raw_handle()),
+                Power::IOCTL_BATTERY_QUERY_STATUS,
+                Some(&self.bws as *const Power::BATTERY_WAIT_STATUS as _),
+                mem::size_of_val(&self.bws) as _,
+                Some(&mut bs as *mut Power::BATTERY_STATUS as _),
+                mem::size_of::<Power::BATTERY_STATUS>() as _,
+                None,
+                None,
+            )
+            .as_bool()
+            {
+                return Err(Error::WinError(windows::core::Error::from_win32()));
+            }
+            Ok(bs)
+        }
+    }
 }
 
 struct OwnedDeviceInfo(DeviceAndDriverInstallation::HDEVINFO);
@@ -1,33 +1,38 @@
 mod battery;
 mod utils;
-#[allow(dead_code)]
 mod winring0;
 
-use crate::{Error, GpuCalculation, Pid};
+use crate::{rate_secs, DiskIo, Error, GpuCalculation, Pid};
 pub use battery::Battery;
 use ferrisetw::parser::Parser;
 use ferrisetw::provider::Provider;
 use ferrisetw::trace::UserTrace;
-use ntapi::ntpsapi;
+use ntapi::{ntexapi, ntobapi, ntpsapi};
 use rand::Rng;
 use regex::Regex;
 use serde::Deserialize;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::mem;
 use std::os::windows::io::BorrowedHandle;
 use std::os::windows::prelude::{AsHandle, AsRawHandle, FromRawHandle, OwnedHandle, RawHandle};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Once, RwLock};
 use std::time::Instant;
+pub(crate) use utils::thread_times;
 pub use utils::{threads_count, threads_info};
 use windows::core::HSTRING;
-use windows::Win32::Foundation;
-use windows::Win32::System::{Performance, Threading};
+use windows::Win32::System::{JobObjects, Performance, Threading};
+use windows::Win32::{Foundation, Security};
+pub use winring0::WinRing0;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ProcessorInfo {
+    // `<node>,<cpu>`
+    pub name: String,
     pub percent_processor_performance: f32,
     pub processor_frequency: f32,
 }
@@ -53,7 +58,12 @@ pub struct Pdh {
     query: PdhHandle,
     total_gpu_counter: isize,
     vram_counter: isize,
+    vram_shared_counter: isize,
     pid_re: Regex,
+    engine_re: Regex,
+    engine_type_re: Regex,
+    // Engine types in the order they were first seen, e.g., 3D, Copy, VideoDecode
+    engine_types: Vec<String>,
     read_buffer: HashMap<Pid, f32>,
 }
 
@@ -82,7 +92,15 @@ impl Pdh {
                 query: PdhHandle(query),
                 total_gpu_counter: 0,
                 vram_counter: 0,
+                vram_shared_counter: 0,
                 pid_re: Regex::new(r"^pid_([0-9]+)_").unwrap(),
+                // e.g., pid_1234_luid_0x00000000_0x0000D1F5_phys_0_eng_3_engtype_3D
+                engine_re: Regex::new(
+                    r"_luid_(0x[0-9A-Fa-f]+_0x[0-9A-Fa-f]+)_(phys_[0-9]+_eng_[0-9]+)",
+                )
+                .unwrap(),
+                engine_type_re: Regex::new(r"_engtype_(.+)$").unwrap(),
+                engine_types: vec![],
                 read_buffer: Default::default(),
             };
 
@@ -107,6 +125,17 @@ impl Pdh {
                 return Err(Error::Pdh(r));
             }
 
+            // shared vram counter, system memory used by the GPU
+            r = Performance::PdhAddCounterW(
+                pdh.query.0,
+                &HSTRING::from("\\GPU Process Memory(*)\\Non Local Usage"),
+                0,
+                &mut pdh.vram_shared_counter,
+            );
+            if r != Performance::PDH_CSTATUS_VALID_DATA {
+                return Err(Error::Pdh(r));
+            }
+
             r = Performance::PdhCollectQueryData(pdh.query.0);
             if r != Performance::PDH_CSTATUS_VALID_DATA {
                 return Err(Error::Pdh(r));
@@ -123,7 +152,10 @@ impl Pdh {
 
     pub fn update(&mut self) {
         unsafe {
-            let r = Performance::PdhCollectQueryData(self.query.0);
+            let mut r = Performance::PdhCollectQueryData(self.query.0);
+            if r == Performance::PDH_CSTATUS_INVALID_DATA {
+                r = Performance::PdhCollectQueryData(self.query.0);
+            }
             self.update_success = r == Performance::PDH_CSTATUS_VALID_DATA;
         }
     }
@@ -141,85 +173,290 @@ impl Pdh {
         let counter = match ty {
             GpuCounterType::Utilization => self.total_gpu_counter,
             GpuCounterType::VRam => self.vram_counter,
+            GpuCounterType::VRamShared => self.vram_shared_counter,
         };
 
-        let mut buffer_size = 0;
-        let mut item_count = 0;
+        let items = counter_array(self.query.0, counter)?;
+        if items.is_empty() {
+            return Some(0.0);
+        }
 
-        unsafe {
-            let mut r = Performance::PdhGetFormattedCounterArrayW(
-                counter,
-                Performance::PDH_FMT_DOUBLE,
-                &mut buffer_size,
-                &mut item_count,
-                None,
-            );
+        let mut pid_values: HashMap<Pid, Vec<f32>> = HashMap::new();
+        for (name, value) in items {
+            if let Some(pid) = self.extract_pid(&name) {
+                pid_values.entry(pid).or_default().push(value);
+            }
+        }
+
+        self.read_buffer.clear();
+        self.read_buffer.extend(
+            pid_values
+                .into_iter()
+                .map(|(pid, values)| (pid, calc.apply(values))),
+        );
+
+        if let Some(pid) = pid {
+            self.read_buffer.remove(&pid)
+        } else {
+            Some(self.read_buffer.drain().map(|(_, v)| v).sum())
+        }
+    }
+
+    // Utilization of every adapter ordered by LUID. Instances of an engine are summed over
+    // processes first, then `calc` is applied across the engines of the adapter
+    pub fn poll_adapters_gpu_usage(&mut self, calc: GpuCalculation) -> Option<Vec<f32>> {
+        if !self.update_success {
+            return None;
+        }
+
+        let items = counter_array(self.query.0, self.total_gpu_counter)?;
+        let mut adapters: BTreeMap<String, HashMap<String, f32>> = BTreeMap::new();
+
+        for (name, value) in items {
+            let Some(caps) = self.engine_re.captures(&name) else {
+                continue;
+            };
+            *adapters
+                .entry(caps[1].to_string())
+                .or_default()
+                .entry(caps[2].to_string())
+                .or_default() += value;
+        }
+
+        Some(
+            adapters
+                .into_values()
+                .map(|engines| calc.apply(engines.into_values()))
+                .collect(),
+        )
+    }
+
+    // Utilization of every engine type, instances of an engine are summed over processes first,
+    // then `calc` is applied across the engines of a type. Types seen once are kept with zeros
+    pub fn poll_engines_gpu_usage(&mut self, calc: GpuCalculation) -> Option<Vec<(String, f32)>> {
+        if !self.update_success {
+            return None;
+        }
+
+        let items = counter_array(self.query.0, self.total_gpu_counter)?;
+        let mut engines: HashMap<String, (String, f32)> = HashMap::new();
+
+        for (name, value) in items {
+            let (Some(caps), Some(ty)) = (
+                self.engine_re.captures(&name),
+                self.engine_type_re.captures(&name),
+            ) else {
+                continue;
+            };
+            engines
+                .entry(format!("{}_{}", &caps[1], &caps[2]))
+                .or_insert_with(|| (ty[1].to_string(), 0.))
+                .1 += value;
+        }
 
-            if r == Performance::PDH_NO_DATA {
-                return Some(0.0);
+        let mut types: HashMap<&str, Vec<f32>> = HashMap::new();
+        for (ty, value) in engines.values() {
+            if !self.engine_types.contains(ty) {
+                self.engine_types.push(ty.clone());
             }
+            types.entry(ty).or_default().push(*value);
+        }
 
-            if r != Performance::PDH_MORE_DATA {
-                return None;
+        Some(
+            self.engine_types
+                .iter()
+                .map(|ty| {
+                    let value = types.remove(ty.as_str()).unwrap_or_default();
+                    (ty.clone(), calc.apply(value))
+                })
+                .collect(),
+        )
+    }
+}
+
+// Instance names and values of a wildcard counter, empty when there is no instance.
+// Invalid data is transient, the query is collected once more before giving up with zeros
+fn counter_array(query: isize, counter: isize) -> Option<Vec<(String, f32)>> {
+    match formatted_counter_array(counter) {
+        Err(Performance::PDH_CSTATUS_INVALID_DATA) => {}
+        r => return r.ok(),
+    }
+
+    unsafe {
+        if Performance::PdhCollectQueryData(query) != Performance::PDH_CSTATUS_VALID_DATA {
+            return Some(vec![]);
+        }
+    }
+
+    match formatted_counter_array(counter) {
+        Err(Performance::PDH_CSTATUS_INVALID_DATA) => Some(vec![]),
+        r => r.ok(),
+    }
+}
+
+fn formatted_counter_array(counter: isize) -> Result<Vec<(String, f32)>, u32> {
+    let mut buffer_size = 0;
+    let mut item_count = 0;
+
+    unsafe {
+        let mut r = Performance::PdhGetFormattedCounterArrayW(
+            counter,
+            Performance::PDH_FMT_DOUBLE,
+            &mut buffer_size,
+            &mut item_count,
+            None,
+        );
+
+        if r == Performance::PDH_NO_DATA {
+            return Ok(vec![]);
+        }
+
+        if r != Performance::PDH_MORE_DATA {
+            return Err(r);
+        }
+
+        let mut buffer: Vec<Performance::PDH_FMT_COUNTERVALUE_ITEM_W> = Vec::with_capacity(
+            buffer_size as usize / mem::size_of::<Performance::PDH_FMT_COUNTERVALUE_ITEM_W>() + 1,
+        );
+
+        r = Performance::PdhGetFormattedCounterArrayW(
+            counter,
+            Performance::PDH_FMT_DOUBLE,
+            &mut buffer_size,
+            &mut item_count,
+            Some(buffer.as_mut_ptr()),
+        );
+
+        if r == Performance::PDH_NO_DATA {
+            return Ok(vec![]);
+        }
+
+        if r != Performance::PDH_CSTATUS_VALID_DATA {
+            return Err(r);
+        }
+        buffer.set_len(item_count as _);
+
+        // Instances without valid data yet, e.g., just created engines, count as zeros
+        Ok(buffer
+            .iter()
+            .filter_map(|item| {
+                let name = item.szName.to_string().ok()?;
+                let value = if item.FmtValue.CStatus == Performance::PDH_CSTATUS_INVALID_DATA {
+                    0.
+                } else {
+                    item.FmtValue.Anonymous.doubleValue as f32
+                };
+                Some((name, value))
+            })
+            .collect())
+    }
+}
+
+// Throughput of every physical disk, the set of disks is fixed when created
+pub struct DiskCounter {
+    update_success: bool,
+    query: PdhHandle,
+    read_counter: isize,
+    write_counter: isize,
+    disks: Vec<String>,
+}
+
+impl DiskCounter {
+    pub fn new() -> Result<Self, Error> {
+        unsafe {
+            let mut query = 0;
+            let mut r = Performance::PdhOpenQueryW(None, 0, &mut query);
+            if r != Performance::PDH_CSTATUS_VALID_DATA {
+                return Err(Error::Pdh(r));
             }
 
-            let mut buffer: Vec<Performance::PDH_FMT_COUNTERVALUE_ITEM_W> = Vec::with_capacity(
-                buffer_size as usize / mem::size_of::<Performance::PDH_FMT_COUNTERVALUE_ITEM_W>()
-                    + 1,
-            );
-            buffer.set_len(item_count as _);
-
-            r = Performance::PdhGetFormattedCounterArrayW(
-                counter,
-                Performance::PDH_FMT_DOUBLE,
-                &mut buffer_size,
-                &mut item_count,
-                Some(buffer.as_mut_ptr()),
-            );
+            let mut counter = Self {
+                update_success: true,
+                query: PdhHandle(query),
+                read_counter: 0,
+                write_counter: 0,
+                disks: vec![],
+            };
 
-            if r == Performance::PDH_NO_DATA {
-                return Some(0.0);
+            r = Performance::PdhAddCounterW(
+                counter.query.0,
+                &HSTRING::from("\\PhysicalDisk(*)\\Disk Read Bytes/sec"),
+                0,
+                &mut counter.read_counter,
+            );
+            if r != Performance::PDH_CSTATUS_VALID_DATA {
+                return Err(Error::Pdh(r));
             }
 
+            r = Performance::PdhAddCounterW(
+                counter.query.0,
+                &HSTRING::from("\\PhysicalDisk(*)\\Disk Write Bytes/sec"),
+                0,
+                &mut counter.write_counter,
+            );
             if r != Performance::PDH_CSTATUS_VALID_DATA {
-                return None;
+                return Err(Error::Pdh(r));
             }
 
-            self.read_buffer.clear();
+            // Rate counters need two collections before their first value
+            r = Performance::PdhCollectQueryData(counter.query.0);
+            if r != Performance::PDH_CSTATUS_VALID_DATA {
+                return Err(Error::Pdh(r));
+            }
 
-            for i in 0..item_count {
-                let name = match buffer[i as usize].szName.to_string() {
-                    Ok(name) => name,
-                    Err(_) => continue,
-                };
+            // Instances are named by index and drive letters, e.g., "0 C: D:"
+            let mut disks: Vec<_> = counter_array(counter.query.0, counter.read_counter)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, _)| name)
+                .filter(|name| name != "_Total")
+                .collect();
+            disks.sort();
+            disks.dedup();
+            counter.disks = disks;
 
-                if let Some(pid) = self.extract_pid(&name) {
-                    let pid_sum = self.read_buffer.entry(pid).or_default();
-                    let value = buffer[i as usize].FmtValue.Anonymous.doubleValue as f32;
+            Ok(counter)
+        }
+    }
 
-                    match calc {
-                        GpuCalculation::Max => {
-                            *pid_sum = pid_sum.max(value);
-                        }
-                        GpuCalculation::Sum => {
-                            *pid_sum += value;
-                        }
-                    }
-                }
-            }
+    pub fn update(&mut self) {
+        unsafe {
+            self.update_success = Performance::PdhCollectQueryData(self.query.0)
+                == Performance::PDH_CSTATUS_VALID_DATA;
         }
+    }
 
-        if let Some(pid) = pid {
-            self.read_buffer.remove(&pid)
+    pub fn disks(&self) -> Vec<DiskIo> {
+        let (read, write): (HashMap<_, _>, HashMap<_, _>) = if self.update_success {
+            (
+                counter_array(self.query.0, self.read_counter)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect(),
+                counter_array(self.query.0, self.write_counter)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect(),
+            )
         } else {
-            Some(self.read_buffer.drain().map(|(_, v)| v).sum())
-        }
+            Default::default()
+        };
+
+        self.disks
+            .iter()
+            .map(|name| DiskIo {
+                name: name.clone(),
+                read: read.get(name).copied().unwrap_or(0.),
+                write: write.get(name).copied().unwrap_or(0.),
+            })
+            .collect()
     }
 }
 
 pub enum GpuCounterType {
     Utilization,
     VRam,
+    VRamShared,
 }
 
 struct EtwProvider {
@@ -228,16 +465,45 @@ struct EtwProvider {
     present_event_id: Vec<u16>,
 }
 
+thread_local! {
+    static IN_ETW_CALLBACK: Cell<bool> = const { Cell::new(false) };
+}
+
+static ETW_PANIC_HOOK: Once = Once::new();
+
+// Malformed records can panic inside ferrisetw, they are counted and dropped silently
+fn catch_etw_callback<F: FnOnce()>(dropped: &AtomicUsize, f: F) {
+    IN_ETW_CALLBACK.with(|c| c.set(true));
+    let r = panic::catch_unwind(AssertUnwindSafe(f));
+    IN_ETW_CALLBACK.with(|c| c.set(false));
+
+    if r.is_err() {
+        dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 pub struct EtwTrace {
     last_update: Instant,
     handler: Arc<RwLock<EtwTraceHandler>>,
-    _trace_guard: UserTrace,
+    dropped: Arc<AtomicUsize>,
+    // A session per provider, so one failing to be enabled doesn't stop the others
+    _trace_guards: Vec<UserTrace>,
 }
 
 impl EtwTrace {
     pub fn new(present: bool, tcp_ip: bool) -> Result<Self, Error> {
-        let mut trace = UserTrace::new().named(format!("precord-{}", rand_string(10)));
+        ETW_PANIC_HOOK.call_once(|| {
+            let hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                if !IN_ETW_CALLBACK.with(Cell::get) {
+                    hook(info);
+                }
+            }));
+        });
+
+        let mut providers = vec![];
         let handler = Arc::new(RwLock::new(EtwTraceHandler::default()));
+        let dropped = Arc::new(AtomicUsize::new(0));
 
         if present {
             for (index, provider_guid) in [
@@ -276,31 +542,37 @@ impl EtwTrace {
             .enumerate()
             {
                 let handler = handler.clone();
+                let dropped = dropped.clone();
                 let provider = Provider::by_guid(provider_guid.guid)
                     .add_callback(move |record, schema_locator| {
-                        // Issue: https://github.com/n4r1b/ferrisetw/issues/26
-                        match schema_locator.event_schema(record) {
-                            Ok(schema) => {
-                                if schema.provider_name() == provider_guid.name
-                                    && provider_guid.present_event_id.contains(&record.event_id())
-                                {
-                                    let mut guard = handler.write().unwrap();
-                                    guard.add_present(index, record.process_id());
+                        catch_etw_callback(&dropped, || {
+                            // Issue: https://github.com/n4r1b/ferrisetw/issues/26
+                            match schema_locator.event_schema(record) {
+                                Ok(schema) => {
+                                    if schema.provider_name() == provider_guid.name
+                                        && provider_guid
+                                            .present_event_id
+                                            .contains(&record.event_id())
+                                    {
+                                        let mut guard = handler.write().unwrap();
+                                        guard.add_present(index, record.process_id());
+                                    }
                                 }
-                            }
-                            Err(_) => {}
-                        };
+                                Err(_) => {}
+                            };
+                        });
                     })
                     .build();
-                trace = trace.enable(provider);
+                providers.push((provider_guid.name, provider));
             }
         }
 
         if tcp_ip {
             let handler = handler.clone();
+            let dropped = dropped.clone();
             let provider = Provider::by_guid("7DD42A49-5329-4832-8DFD-43D979153A88") // Microsoft-Windows-Kernel-Network
                 .add_callback(move |record, schema_locator| {
-                    match schema_locator.event_schema(record) {
+                    catch_etw_callback(&dropped, || match schema_locator.event_schema(record) {
                         Ok(schema) => {
                             if schema.provider_name() == "Microsoft-Windows-Kernel-Network" {
                                 match record.event_id() {
@@ -336,21 +608,47 @@ impl EtwTrace {
                             }
                         }
                         Err(_) => {}
-                    };
+                    });
                 })
                 .build();
-            trace = trace.enable(provider);
+            providers.push(("Microsoft-Windows-Kernel-Network", provider));
+        }
+
+        let mut trace_guards = vec![];
+        let mut last_err = None;
+        for (name, provider) in providers {
+            match UserTrace::new()
+                .named(format!("precord-{}", rand_string(10)))
+                .enable(provider)
+                .start_and_process()
+            {
+                Ok(trace_guard) => trace_guards.push(trace_guard),
+                Err(err) => {
+                    log::warn!("Skip etw provider {}: {:?}", name, err);
+                    last_err = Some(err);
+                }
+            }
         }
 
-        let trace_guard = trace.start_and_process().map_err(Error::Etw)?;
+        if trace_guards.is_empty() {
+            if let Some(err) = last_err {
+                return Err(Error::Etw(err));
+            }
+        }
 
         Ok(Self {
             last_update: Instant::now(),
             handler,
-            _trace_guard: trace_guard,
+            dropped,
+            _trace_guards: trace_guards,
         })
     }
 
+    // Records dropped because a callback panicked
+    pub fn dropped_events(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
     pub fn fps(&self, pid: Pid) -> f32 {
         self.handler.read().unwrap().fps(pid as _)
     }
@@ -365,7 +663,7 @@ impl EtwTrace {
 
     pub fn update(&mut self) {
         let now = Instant::now();
-        let d = (now - self.last_update).as_secs_f32();
+        let d = rate_secs(now - self.last_update);
         for value in self.handler.write().unwrap().trace_events.values_mut() {
             value.present_per_sec = value.present.into_iter().max().unwrap() as f32 / d;
             value.net_send_per_sec = (value.net_send as f32 / d) as _;
@@ -444,29 +742,78 @@ pub struct VmCounter {
     process_counters: Vec<ProcessVmCounter>,
 }
 
+impl ProcessVmCounter {
+    fn open(pid: Pid) -> windows::core::Result<Self> {
+        let h =
+            unsafe { Threading::OpenProcess(Threading::PROCESS_QUERY_INFORMATION, false, pid)? };
+        let handle = unsafe { OwnedHandle::from_raw_handle(h.0 as _) };
+        let io = process_io_counters(handle.as_handle()).unwrap_or_default();
+        Ok(Self {
+            pid,
+            handle,
+            valid: true,
+            mem: 0,
+            alloc: 0,
+            last_io: io,
+            read_bytes: 0,
+            write_bytes: 0,
+            read_ops: 0,
+            write_ops: 0,
+            last_page_faults: None,
+            page_faults: 0,
+            last_ctx_switches: None,
+            ctx_switches: 0,
+        })
+    }
+}
+
 impl VmCounter {
-    pub fn new<T: IntoIterator<Item = Pid>>(pids: T) -> Self {
-        Self {
-            process_counters: pids
-                .into_iter()
-                .filter_map(|pid| {
-                    let h = unsafe {
-                        Threading::OpenProcess(Threading::PROCESS_QUERY_INFORMATION, false, pid)
-                    }
-                    .ok()?;
-                    Some(ProcessVmCounter {
-                        pid,
-                        handle: unsafe { OwnedHandle::from_raw_handle(h.0 as _) },
-                        valid: true,
-                        mem: 0,
-                        alloc: 0,
-                    })
-                })
-                .collect(),
+    // Pids racing to exit are skipped, access denied is returned to raise privileges
+    pub fn new<T: IntoIterator<Item = Pid>>(pids: T) -> Result<Self, Error> {
+        let mut counter = Self {
+            process_counters: vec![],
+        };
+        for pid in pids {
+            if counter.process_counters.iter().any(|p| p.pid == pid) {
+                continue;
+            }
+            match ProcessVmCounter::open(pid) {
+                Ok(p) => counter.process_counters.push(p),
+                Err(err) if err.code() == Foundation::E_ACCESSDENIED => {
+                    return Err(Error::AccessDenied)
+                }
+                Err(err) => log::warn!("Skip pid {}: {}", pid, err),
+            }
         }
+        Ok(counter)
+    }
+
+    pub fn add_pids<T: IntoIterator<Item = Pid>>(&mut self, pids: T) {
+        for pid in pids {
+            if self.process_counters.iter().any(|p| p.pid == pid) {
+                continue;
+            }
+            match ProcessVmCounter::open(pid) {
+                Ok(p) => self.process_counters.push(p),
+                Err(err) => log::warn!("Skip pid {}: {}", pid, err),
+            }
+        }
+    }
+
+    // Dropping the counters closes their process handles
+    pub fn remove_pids(&mut self, pids: &[Pid]) {
+        self.process_counters.retain(|p| !pids.contains(&p.pid));
     }
 
     pub fn update(&mut self) {
+        let pids: Vec<_> = self
+            .process_counters
+            .iter()
+            .filter(|p| p.valid)
+            .map(|p| p.pid)
+            .collect();
+        let ctx_switches = processes_ctx_switches(&pids);
+
         for p in self.process_counters.iter_mut() {
             if !p.valid {
                 continue;
@@ -485,7 +832,35 @@ impl VmCounter {
                     if r.is_ok() {
                         p.mem = info.PrivateWorkingSetSize;
                         p.alloc = info.CountersEx.PrivateUsage;
+
+                        let page_faults = info.CountersEx.PageFaultCount;
+                        if let Some(last) = p.last_page_faults {
+                            p.page_faults = page_faults.saturating_sub(last);
+                        }
+                        p.last_page_faults = Some(page_faults);
+                    }
+                }
+
+                if let Some(&c) = ctx_switches.get(&p.pid) {
+                    if let Some(last) = p.last_ctx_switches {
+                        p.ctx_switches = c.saturating_sub(last);
                     }
+                    p.last_ctx_switches = Some(c);
+                }
+
+                if let Some(io) = process_io_counters(p.handle.as_handle()) {
+                    let last = &p.last_io;
+                    p.read_bytes = io.ReadTransferCount.saturating_sub(last.ReadTransferCount);
+                    p.write_bytes = io
+                        .WriteTransferCount
+                        .saturating_sub(last.WriteTransferCount);
+                    p.read_ops = io
+                        .ReadOperationCount
+                        .saturating_sub(last.ReadOperationCount);
+                    p.write_ops = io
+                        .WriteOperationCount
+                        .saturating_sub(last.WriteOperationCount);
+                    p.last_io = io;
                 }
             } else {
                 p.valid = false;
@@ -507,6 +882,48 @@ impl VmCounter {
             .map(|p| p.alloc)
     }
 
+    pub fn process_read_bytes(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.valid)
+            .map(|p| p.read_bytes)
+    }
+
+    pub fn process_write_bytes(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.valid)
+            .map(|p| p.write_bytes)
+    }
+
+    pub fn process_read_ops(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.valid)
+            .map(|p| p.read_ops)
+    }
+
+    pub fn process_write_ops(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.valid)
+            .map(|p| p.write_ops)
+    }
+
+    pub fn process_page_faults(&self, pid: Pid) -> Option<u32> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.valid)
+            .map(|p| p.page_faults)
+    }
+
+    pub fn process_ctx_switches(&self, pid: Pid) -> Option<u64> {
+        self.process_counters
+            .iter()
+            .find(|p| p.pid == pid && p.valid)
+            .map(|p| p.ctx_switches)
+    }
+
     pub fn process_handles(&mut self, pid: Pid) -> Option<u32> {
         let p = self
             .process_counters
@@ -537,6 +954,249 @@ struct ProcessVmCounter {
     valid: bool,
     mem: usize,
     alloc: usize,
+    last_io: Threading::IO_COUNTERS,
+    read_bytes: u64,
+    write_bytes: u64,
+    read_ops: u64,
+    write_ops: u64,
+    last_page_faults: Option<u32>,
+    page_faults: u32,
+    last_ctx_switches: Option<u64>,
+    ctx_switches: u64,
+}
+
+// Bytes and operations of reads and writes
+fn process_io_counters(handle: BorrowedHandle) -> Option<Threading::IO_COUNTERS> {
+    unsafe {
+        let mut io: Threading::IO_COUNTERS = mem::zeroed();
+        if Threading::GetProcessIoCounters(windows_raw_handle(handle.as_raw_handle()), &mut io)
+            .as_bool()
+        {
+            Some(io)
+        } else {
+            None
+        }
+    }
+}
+
+// Sum of context switches of all threads, per process
+fn processes_ctx_switches(pids: &[Pid]) -> HashMap<Pid, u64> {
+    let mut ctx_switches = HashMap::new();
+    if pids.is_empty() {
+        return ctx_switches;
+    }
+
+    unsafe {
+        // u64 for the alignment of SYSTEM_PROCESS_INFORMATION
+        let mut buf: Vec<u64> = Vec::with_capacity(1 << 16);
+
+        loop {
+            let mut return_length = 0;
+            let r = ntexapi::NtQuerySystemInformation(
+                ntexapi::SystemProcessInformation,
+                buf.as_mut_ptr() as _,
+                (buf.capacity() * mem::size_of::<u64>()) as _,
+                &mut return_length,
+            );
+            if r == Foundation::STATUS_INFO_LENGTH_MISMATCH.0 {
+                buf.reserve(return_length as usize / mem::size_of::<u64>() + 1);
+                continue;
+            }
+            if r != Foundation::STATUS_SUCCESS.0 {
+                return ctx_switches;
+            }
+            break;
+        }
+
+        let base = buf.as_ptr() as *const u8;
+        let mut offset = 0;
+        loop {
+            let info = base.add(offset) as *const ntexapi::SYSTEM_PROCESS_INFORMATION;
+            let pid = (*info).UniqueProcessId as usize as Pid;
+            if pids.contains(&pid) {
+                let threads = std::slice::from_raw_parts(
+                    ptr::addr_of!((*info).Threads) as *const ntexapi::SYSTEM_THREAD_INFORMATION,
+                    (*info).NumberOfThreads as usize,
+                );
+                ctx_switches.insert(pid, threads.iter().map(|t| t.ContextSwitches as u64).sum());
+            }
+
+            if (*info).NextEntryOffset == 0 {
+                break;
+            }
+            offset += (*info).NextEntryOffset as usize;
+        }
+    }
+
+    ctx_switches
+}
+
+// Access masks of synchronous pipes, querying their names can block forever
+const BLOCKING_ACCESS: [u32; 4] = [0x0012_019f, 0x001a_019f, 0x0012_0189, 0x0010_0000];
+
+// Handles of the process opened on `\Device\Afd`, i.e., sockets
+pub fn process_sockets(pid: Pid) -> Option<u32> {
+    unsafe {
+        let process = Threading::OpenProcess(Threading::PROCESS_DUP_HANDLE, false, pid).ok()?;
+        let process = OwnedHandle::from_raw_handle(process.0 as _);
+
+        // u64 for the alignment of SYSTEM_HANDLE_INFORMATION_EX
+        let mut buf: Vec<u64> = Vec::with_capacity(1 << 16);
+
+        loop {
+            let mut return_length = 0;
+            let r = ntexapi::NtQuerySystemInformation(
+                ntexapi::SystemExtendedHandleInformation,
+                buf.as_mut_ptr() as _,
+                (buf.capacity() * mem::size_of::<u64>()) as _,
+                &mut return_length,
+            );
+            if r == Foundation::STATUS_INFO_LENGTH_MISMATCH.0 {
+                // Handles may be opened before the next call
+                buf.reserve(return_length as usize / mem::size_of::<u64>() + (1 << 12));
+                continue;
+            }
+            if r != Foundation::STATUS_SUCCESS.0 {
+                return None;
+            }
+            break;
+        }
+
+        let info = buf.as_ptr() as *const ntexapi::SYSTEM_HANDLE_INFORMATION_EX;
+        let handles = std::slice::from_raw_parts(
+            ptr::addr_of!((*info).Handles) as *const ntexapi::SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX,
+            (*info).NumberOfHandles,
+        );
+
+        let mut name_buf: Vec<u64> = vec![0; 256];
+        let mut count = 0;
+
+        for h in handles {
+            if h.UniqueProcessId != pid as usize || BLOCKING_ACCESS.contains(&h.GrantedAccess) {
+                continue;
+            }
+
+            let mut dup = Foundation::HANDLE::default();
+            if !Foundation::DuplicateHandle(
+                windows_raw_handle(process.as_raw_handle()),
+                Foundation::HANDLE(h.HandleValue as _),
+                Threading::GetCurrentProcess(),
+                &mut dup,
+                0,
+                false,
+                Foundation::DUPLICATE_SAME_ACCESS,
+            )
+            .as_bool()
+            {
+                continue;
+            }
+            let dup = OwnedHandle::from_raw_handle(dup.0 as _);
+
+            let r = ntobapi::NtQueryObject(
+                dup.as_raw_handle() as _,
+                ntobapi::ObjectNameInformation,
+                name_buf.as_mut_ptr() as _,
+                (name_buf.len() * mem::size_of::<u64>()) as _,
+                ptr::null_mut(),
+            );
+            if r != Foundation::STATUS_SUCCESS.0 {
+                continue;
+            }
+
+            let name = &(*(name_buf.as_ptr() as *const ntobapi::OBJECT_NAME_INFORMATION)).Name;
+            if name.Buffer.is_null() {
+                continue;
+            }
+            let name = std::slice::from_raw_parts(name.Buffer, name.Length as usize / 2);
+            if String::from_utf16_lossy(name).starts_with("\\Device\\Afd") {
+                count += 1;
+            }
+        }
+
+        Some(count)
+    }
+}
+
+// JOB_OBJECT_QUERY of Win32_System_SystemServices
+const JOB_OBJECT_QUERY: u32 = 0x0004;
+
+// Processes assigned to the named job object, None when it can't be opened
+pub fn job_pids(name: &str) -> Option<Vec<Pid>> {
+    unsafe {
+        let job = JobObjects::OpenJobObjectW(JOB_OBJECT_QUERY, false, &HSTRING::from(name)).ok()?;
+        let job = OwnedHandle::from_raw_handle(job.0 as _);
+
+        // Header of two u32 followed by the ids
+        let mut capacity = 64;
+        loop {
+            let mut buf: Vec<usize> = vec![0; capacity + 2];
+            let r = JobObjects::QueryInformationJobObject(
+                windows_raw_handle(job.as_raw_handle()),
+                JobObjects::JobObjectBasicProcessIdList,
+                buf.as_mut_ptr() as _,
+                (buf.len() * mem::size_of::<usize>()) as _,
+                None,
+            );
+            let list = &*(buf.as_ptr() as *const JobObjects::JOBOBJECT_BASIC_PROCESS_ID_LIST);
+            if !r.as_bool() {
+                if Foundation::GetLastError() == Foundation::ERROR_MORE_DATA {
+                    capacity = list.NumberOfAssignedProcesses as usize + 16;
+                    continue;
+                }
+                return None;
+            }
+
+            let ids = std::slice::from_raw_parts(
+                ptr::addr_of!(list.ProcessIdList) as *const usize,
+                list.NumberOfProcessIdsInList as usize,
+            );
+            return Some(ids.iter().map(|&id| id as Pid).collect());
+        }
+    }
+}
+
+pub fn process_exists(pid: Pid) -> Result<bool, Error> {
+    unsafe {
+        match Threading::OpenProcess(Threading::PROCESS_QUERY_LIMITED_INFORMATION, false, pid) {
+            Ok(h) => {
+                drop(OwnedHandle::from_raw_handle(h.0 as _));
+                Ok(true)
+            }
+            Err(_) if Foundation::GetLastError() == Foundation::ERROR_ACCESS_DENIED => {
+                Err(Error::AccessDenied)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+// ETW sessions need an elevated token
+pub fn is_elevated() -> bool {
+    unsafe {
+        let mut token = Foundation::HANDLE::default();
+        if !Threading::OpenProcessToken(
+            Threading::GetCurrentProcess(),
+            Security::TOKEN_QUERY,
+            &mut token,
+        )
+        .as_bool()
+        {
+            return false;
+        }
+        let token = OwnedHandle::from_raw_handle(token.0 as _);
+
+        let mut elevation = Security::TOKEN_ELEVATION::default();
+        let mut len = 0;
+        Security::GetTokenInformation(
+            windows_raw_handle(token.as_raw_handle()),
+            Security::TokenElevation,
+            Some(&mut elevation as *mut _ as _),
+            mem::size_of::<Security::TOKEN_ELEVATION>() as _,
+            &mut len,
+        )
+        .as_bool()
+            && elevation.TokenIsElevated != 0
+    }
 }
 
 // Source from sysinfo
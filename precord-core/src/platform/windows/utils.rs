@@ -1,4 +1,4 @@
-use crate::{Error, Pid};
+use crate::{Error, Pid, ThreadTime};
 use ntapi::winapi::um::winnt;
 use std::os::windows::prelude::{AsRawHandle, FromRawHandle, OwnedHandle};
 use std::time::Duration;
@@ -181,6 +181,50 @@ pub fn threads_info(pid: Pid, nb_cpus: u32) -> Result<Vec<ThreadInfo>, Error> {
     Ok(threads_info)
 }
 
+// Without sleeping, usage comes from the difference between two calls
+pub(crate) fn thread_times(pid: Pid) -> Option<Vec<ThreadTime>> {
+    // Filetimes are in 100ns
+    fn nanos(ft: &Foundation::FILETIME) -> u64 {
+        (((ft.dwHighDateTime as u64) << 32) | ft.dwLowDateTime as u64) * 100
+    }
+
+    let threads = threads(pid);
+    if threads.is_empty() {
+        return None;
+    }
+
+    Some(
+        threads
+            .into_iter()
+            .filter_map(|tid| unsafe {
+                let raw_handle =
+                    Threading::OpenThread(Threading::THREAD_QUERY_LIMITED_INFORMATION, false, tid)
+                        .ok()?;
+                let handle = OwnedHandle::from_raw_handle(raw_handle.0 as _);
+
+                let mut ignore = mem::zeroed();
+                let mut kernel = mem::zeroed();
+                let mut user = mem::zeroed();
+                Threading::GetThreadTimes(
+                    super::windows_raw_handle(handle.as_raw_handle()),
+                    &mut ignore,
+                    &mut ignore,
+                    &mut kernel,
+                    &mut user,
+                )
+                .ok()
+                .ok()?;
+
+                Some(ThreadTime {
+                    id: tid as _,
+                    name: String::new(),
+                    cpu_time: Duration::from_nanos(nanos(&kernel) + nanos(&user)),
+                })
+            })
+            .collect(),
+    )
+}
+
 pub fn threads_count(pid: Pid) -> Option<u32> {
     Some(threads(pid).len() as u32)
 }
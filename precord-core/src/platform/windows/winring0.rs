@@ -7,7 +7,8 @@ const IA32_PACKAGE_THERM_STATUS: c_uint = 0x1b1;
 // https://github.com/GermanAizek/WinRing0
 pub struct WinRing0 {
     rdmsr: libloading::Symbol<'static, Rdmsr>,
-    lib: libloading::Library,
+    deinitialize_ols: libloading::Symbol<'static, DeinitializeOls>,
+    _lib: libloading::Library,
 }
 
 impl WinRing0 {
@@ -22,14 +23,49 @@ impl WinRing0 {
 
         unsafe {
             let lib = libloading::Library::new(dll_name)?;
+            let initialize_ols: libloading::Symbol<InitializeOls> = lib.get(b"InitializeOls")?;
+            let deinitialize_ols: libloading::Symbol<DeinitializeOls> =
+                lib.get(b"DeinitializeOls")?;
             let rdmsr: libloading::Symbol<Rdmsr> = lib.get(b"Rdmsr")?;
+
+            // Driver can't be loaded without administrator
+            if !initialize_ols().as_bool() {
+                return Err(libloading::Error::DlOpenUnknown);
+            }
+
             Ok(Self {
                 rdmsr: mem::transmute(rdmsr),
-                lib,
+                deinitialize_ols: mem::transmute(deinitialize_ols),
+                _lib: lib,
             })
         }
     }
+
+    fn read_msr(&self, index: c_uint) -> Option<c_uint> {
+        let mut eax = 0;
+        let mut edx = 0;
+        if (self.rdmsr)(index, &mut eax, &mut edx).as_bool() {
+            Some(eax)
+        } else {
+            None
+        }
+    }
+
+    // Intel only, temperature = TjMax - digital readout
+    pub fn package_temperature(&self) -> Option<f32> {
+        let tj_max = self.read_msr(IA32_TEMPERATURE_TARGET)? >> 16 & 0xff;
+        let readout = self.read_msr(IA32_PACKAGE_THERM_STATUS)? >> 16 & 0x7f;
+        Some(tj_max.saturating_sub(readout) as f32)
+    }
+}
+
+impl Drop for WinRing0 {
+    fn drop(&mut self) {
+        (self.deinitialize_ols)();
+    }
 }
 
 type Rdmsr =
     extern "system" fn(c_uint, *mut c_uint, *mut c_uint) -> windows::Win32::Foundation::BOOL;
+type InitializeOls = extern "system" fn() -> windows::Win32::Foundation::BOOL;
+type DeinitializeOls = extern "system" fn();
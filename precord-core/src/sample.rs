@@ -0,0 +1,59 @@
+use crate::{GpuCalculation, Pid};
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ProcessCategory {
+    Cpu,
+    Mem,
+    Alloc,
+    Gpu(GpuCalculation),
+    Vram(GpuCalculation),
+    VramShared(GpuCalculation),
+    Fps,
+    NetIn,
+    NetOut,
+    DiskRead,
+    DiskWrite,
+    DiskReadOps,
+    DiskWriteOps,
+    Kobject,
+    MachPorts,
+    Fds,
+    Sockets,
+    Thread,
+    PageFaults,
+    CtxSwitches,
+    IoWait,
+    Power,
+    Uptime,
+}
+
+// Values are in the units of the granular `System::process_*` methods,
+// `None` when the category isn't requested or can't be sampled
+#[derive(Debug, Default, Clone)]
+pub struct ProcessSample {
+    pub pid: Pid,
+    pub cpu_usage: Option<f32>,
+    pub mem: Option<usize>,
+    pub alloc: Option<usize>,
+    pub gpu_usage: Option<f32>,
+    pub vram: Option<f32>,
+    pub vram_shared: Option<f32>,
+    pub fps: Option<f32>,
+    pub net_traffic_in: Option<u32>,
+    pub net_traffic_out: Option<u32>,
+    pub disk_read: Option<f32>,
+    pub disk_write: Option<f32>,
+    pub disk_read_ops: Option<f32>,
+    pub disk_write_ops: Option<f32>,
+    pub kobject: Option<u32>,
+    pub mach_ports: Option<u32>,
+    pub fds: Option<u32>,
+    pub sockets: Option<u32>,
+    pub threads: Option<u32>,
+    pub page_faults: Option<f32>,
+    pub ctx_switches: Option<f32>,
+    pub io_wait: Option<f32>,
+    pub power: Option<f32>,
+    pub uptime: Option<Duration>,
+}
@@ -0,0 +1,82 @@
+use crate::{Error, Features, Pid, ProcessCategory, ProcessSample, System};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// Samples of every pid, or the error of the update they were taken after
+pub type SamplerResult = Result<Vec<ProcessSample>, Error>;
+
+// A `System` owned by a dedicated thread, sampled every interval. Dropping the sampler stops the thread
+pub struct Sampler {
+    samples: Receiver<SamplerResult>,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Sampler {
+    pub(crate) fn spawn<T>(
+        features: Features,
+        pids: T,
+        categories: Vec<ProcessCategory>,
+        interval: Duration,
+    ) -> Result<Self, Error>
+    where
+        T: IntoIterator<Item = Pid> + Clone + Send + 'static,
+    {
+        let (init_tx, init_rx) = mpsc::sync_channel(1);
+        let (samples_tx, samples_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || {
+            // COM objects of WMI are bound to the thread creating them
+            let mut system = match System::with_interval(features, pids, interval) {
+                Ok(system) => {
+                    let _ = init_tx.send(Ok(()));
+                    system
+                }
+                Err(err) => {
+                    let _ = init_tx.send(Err(err));
+                    return;
+                }
+            };
+
+            // Waiting on the stop channel wakes up as soon as the sampler is dropped
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                let r = system
+                    .update(Instant::now())
+                    .map(|_| system.sample(&categories));
+                if samples_tx.send(r).is_err() {
+                    break;
+                }
+            }
+        });
+
+        match init_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                samples: samples_rx,
+                stop: Some(stop_tx),
+                handle: Some(handle),
+            }),
+            Ok(Err(err)) => Err(err),
+            // The thread panicked creating the system
+            Err(_) => match handle.join() {
+                Err(panic) => std::panic::resume_unwind(panic),
+                Ok(_) => unreachable!(),
+            },
+        }
+    }
+
+    // Blocks on `recv`, or polls with `try_recv` from an event loop
+    pub fn samples(&self) -> &Receiver<SamplerResult> {
+        &self.samples
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
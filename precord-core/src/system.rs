@@ -1,25 +1,41 @@
 use crate::platform;
+#[cfg(target_os = "linux")]
+use crate::platform::linux::{DiskCounter, DrmCounter, EnergyMeter, FrameTracer, ProcCounter};
 #[cfg(target_os = "macos")]
-use crate::platform::macos::{get_pid_responsible, CommandSource, IOKitRegistry};
+use crate::platform::macos::{
+    get_pid_responsible, CommandSource, DiskCounter, EnergyCounter, IOKitRegistry,
+};
 #[cfg(target_os = "windows")]
-use crate::platform::windows::{EtwTrace, Pdh, ProcessorInfo, VmCounter};
-use crate::{Error, GpuCalculation, Pid};
+use crate::platform::windows::{DiskCounter, EtwTrace, Pdh, ProcessorInfo, VmCounter};
+use crate::thread_counter::ThreadCounter;
+use crate::{
+    rate_secs, CpuFrequency, CpuScale, DiskIo, Error, GpuCalculation, GpuDevice, Pid, ProcessBrief,
+    ProcessCategory, ProcessSample, Sampler, ThreadUsage,
+};
 use bitflags::bitflags;
-use std::time::{Duration, Instant};
-use sysinfo::{CpuRefreshKind, ProcessRefreshKind};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sysinfo::{CpuRefreshKind, MemoryRefreshKind, ProcessRefreshKind};
 
 pub struct System {
     last_update: Instant,
     last_duration: Duration,
     features: Features,
+    pids: Vec<Pid>,
+    cpu_scale: CpuScale,
     sysinfo_system: Option<sysinfo::System>,
     refresh_kind: sysinfo::RefreshKind,
+    networks: Option<sysinfo::Networks>,
+    disk_counter: Option<DiskCounter>,
+    thread_counter: Option<ThreadCounter>,
     #[cfg(target_os = "macos")]
     command_source: Option<CommandSource>,
     #[cfg(target_os = "macos")]
     ioreg: Option<IOKitRegistry>,
     #[cfg(target_os = "macos")]
     smc: Option<smc::SMC>,
+    #[cfg(target_os = "macos")]
+    energy_counter: Option<EnergyCounter>,
     #[cfg(target_os = "windows")]
     pdh: Option<Pdh>,
     #[cfg(target_os = "windows")]
@@ -30,25 +46,52 @@ pub struct System {
     vm_counter: Option<VmCounter>,
     #[cfg(target_os = "windows")]
     battery: Option<platform::windows::Battery>,
+    #[cfg(target_os = "windows")]
+    winring0: Option<platform::windows::WinRing0>,
+    #[cfg(target_os = "linux")]
+    proc_counter: Option<ProcCounter>,
+    #[cfg(target_os = "linux")]
+    drm_counter: Option<DrmCounter>,
+    #[cfg(target_os = "linux")]
+    energy_meter: Option<EnergyMeter>,
+    #[cfg(target_os = "linux")]
+    frame_tracer: Option<FrameTracer>,
 }
 
 impl System {
     pub fn new<T: IntoIterator<Item = Pid> + Clone>(
         features: Features,
         pids: T,
+    ) -> Result<Self, Error> {
+        Self::with_interval(features, pids, Duration::from_secs(1))
+    }
+
+    // `interval` is what `update` is called at, sources streaming samples on their own, i.e.,
+    // powermetrics on macOS, are started at it
+    pub fn with_interval<T: IntoIterator<Item = Pid> + Clone>(
+        features: Features,
+        pids: T,
+        interval: Duration,
     ) -> Result<Self, Error> {
         let mut system = System {
             last_update: Instant::now(),
             last_duration: Duration::ZERO,
             features,
+            pids: pids.clone().into_iter().collect(),
+            cpu_scale: CpuScale::default(),
             sysinfo_system: None,
             refresh_kind: sysinfo::RefreshKind::default().with_cpu(CpuRefreshKind::everything()),
+            networks: None,
+            disk_counter: None,
+            thread_counter: None,
             #[cfg(target_os = "macos")]
             command_source: None,
             #[cfg(target_os = "macos")]
             ioreg: None,
             #[cfg(target_os = "macos")]
             smc: None,
+            #[cfg(target_os = "macos")]
+            energy_counter: None,
             #[cfg(target_os = "windows")]
             pdh: None,
             #[cfg(target_os = "windows")]
@@ -59,18 +102,33 @@ impl System {
             vm_counter: None,
             #[cfg(target_os = "windows")]
             battery: None,
+            #[cfg(target_os = "windows")]
+            winring0: None,
+            #[cfg(target_os = "linux")]
+            proc_counter: None,
+            #[cfg(target_os = "linux")]
+            drm_counter: None,
+            #[cfg(target_os = "linux")]
+            energy_meter: None,
+            #[cfg(target_os = "linux")]
+            frame_tracer: None,
         };
 
         let mut use_sysinfo_system = false;
         if features.contains(Features::PROCESS) {
             system.refresh_kind = system
                 .refresh_kind
-                .with_processes(ProcessRefreshKind::everything());
+                .with_processes(ProcessRefreshKind::everything())
+                .with_memory(MemoryRefreshKind::everything());
             use_sysinfo_system = true;
 
             #[cfg(target_os = "windows")]
             {
-                system.vm_counter = Some(VmCounter::new(pids.clone()));
+                system.vm_counter = Some(VmCounter::new(pids.clone())?);
+            }
+            #[cfg(target_os = "linux")]
+            {
+                system.proc_counter = Some(ProcCounter::new(pids.clone()));
             }
         }
         if features.contains(Features::SMC) | features.contains(Features::CPU_FREQUENCY) {
@@ -82,26 +140,75 @@ impl System {
             system.sysinfo_system = Some(sysinfo_system);
         }
 
+        if features.contains(Features::NETWORK) {
+            system.networks = Some(sysinfo::Networks::new_with_refreshed_list());
+        }
+
+        if features.contains(Features::THREADS) {
+            system.thread_counter = Some(ThreadCounter::new(pids.clone()));
+        }
+
+        if features.contains(Features::DISK) {
+            #[cfg(target_os = "macos")]
+            {
+                system.disk_counter = Some(DiskCounter::new());
+            }
+            #[cfg(target_os = "windows")]
+            {
+                system.disk_counter = Some(DiskCounter::new()?);
+            }
+            #[cfg(target_os = "linux")]
+            {
+                system.disk_counter = DiskCounter::new();
+            }
+        }
+
+        // Categories read from powermetrics, the others don't start it
+        #[cfg(target_os = "macos")]
+        let power_metrics = features.intersects(Features::PROCESS_GPU | Features::CPU_FREQUENCY);
+        // Only powermetrics streams samples at its own interval
+        #[cfg(not(target_os = "macos"))]
+        let _ = interval;
+
         if features.contains(Features::GPU) {
             #[cfg(target_os = "macos")]
             {
                 system.ioreg = Some(IOKitRegistry::new(features.contains(Features::GPU)));
+                // Per-process GPU time comes from powermetrics, system GPU from IOKit
+                if features.contains(Features::PROCESS_GPU) {
+                    system.command_source = Some(CommandSource::new(
+                        pids.clone(),
+                        power_metrics,
+                        interval,
+                        features.contains(Features::NET_TRAFFIC),
+                        features.contains(Features::FPS),
+                        features.contains(Features::K_OBJECT),
+                    )?);
+                }
             }
             #[cfg(target_os = "windows")]
             {
                 system.pdh = Some(Pdh::new()?);
             }
+            #[cfg(target_os = "linux")]
+            {
+                system.drm_counter = Some(DrmCounter::new(pids.clone()));
+            }
         }
 
         if features.contains(Features::CPU_FREQUENCY) {
             #[cfg(target_os = "macos")]
             {
-                system.command_source = Some(CommandSource::new(
-                    pids.clone(),
-                    features.contains(Features::NET_TRAFFIC),
-                    features.contains(Features::FPS),
-                    features.contains(Features::K_OBJECT),
-                ));
+                if system.command_source.is_none() {
+                    system.command_source = Some(CommandSource::new(
+                        pids.clone(),
+                        power_metrics,
+                        interval,
+                        features.contains(Features::NET_TRAFFIC),
+                        features.contains(Features::FPS),
+                        features.contains(Features::K_OBJECT),
+                    )?);
+                }
             }
             #[cfg(target_os = "windows")]
             {
@@ -114,14 +221,16 @@ impl System {
         if features.contains(Features::FPS) {
             #[cfg(target_os = "macos")]
             {
-                system.command_source = Some(system.command_source.unwrap_or_else(|| {
-                    CommandSource::new(
+                if system.command_source.is_none() {
+                    system.command_source = Some(CommandSource::new(
                         pids.clone(),
+                        power_metrics,
+                        interval,
                         features.contains(Features::NET_TRAFFIC),
                         features.contains(Features::FPS),
                         features.contains(Features::K_OBJECT),
-                    )
-                }));
+                    )?);
+                }
             }
             #[cfg(target_os = "windows")]
             {
@@ -130,6 +239,10 @@ impl System {
                     features.contains(Features::NET_TRAFFIC),
                 )?);
             }
+            #[cfg(target_os = "linux")]
+            {
+                system.frame_tracer = Some(FrameTracer::new()?);
+            }
         }
 
         if features.contains(Features::SMC) {
@@ -146,20 +259,28 @@ impl System {
                 }
 
                 system.battery = platform::windows::Battery::new()?;
+                // Optional, WinRing0x64.dll is shipped by users
+                system.winring0 = platform::windows::WinRing0::new().ok();
+            }
+            #[cfg(target_os = "linux")]
+            {
+                system.energy_meter = EnergyMeter::new();
             }
         }
 
         if features.contains(Features::NET_TRAFFIC) {
             #[cfg(target_os = "macos")]
             {
-                system.command_source = Some(system.command_source.unwrap_or_else(|| {
-                    CommandSource::new(
+                if system.command_source.is_none() {
+                    system.command_source = Some(CommandSource::new(
                         pids.clone(),
+                        power_metrics,
+                        interval,
                         features.contains(Features::NET_TRAFFIC),
                         features.contains(Features::FPS),
                         features.contains(Features::K_OBJECT),
-                    )
-                }));
+                    )?);
+                }
             }
             #[cfg(target_os = "windows")]
             {
@@ -169,36 +290,66 @@ impl System {
             }
         }
 
+        if features.contains(Features::ENERGY) {
+            #[cfg(target_os = "macos")]
+            {
+                system.energy_counter = Some(EnergyCounter::new(pids.clone()));
+            }
+        }
+
         if features.contains(Features::K_OBJECT) {
             #[cfg(target_os = "macos")]
             {
-                system.command_source = Some(system.command_source.unwrap_or_else(|| {
-                    CommandSource::new(
+                if system.command_source.is_none() {
+                    system.command_source = Some(CommandSource::new(
                         pids,
+                        power_metrics,
+                        interval,
                         features.contains(Features::NET_TRAFFIC),
                         features.contains(Features::FPS),
                         features.contains(Features::K_OBJECT),
-                    )
-                }));
+                    )?);
+                }
             }
         }
 
         Ok(system)
     }
 
-    pub fn update(&mut self, now: Instant) {
+    // Failed sources keep their previous values, the error is returned after every source is updated
+    pub fn update(&mut self, now: Instant) -> Result<(), Error> {
         self.last_duration = now - self.last_update;
         self.last_update = now;
 
         if let Some(sysinfo_system) = &mut self.sysinfo_system {
             sysinfo_system.refresh_specifics(self.refresh_kind);
         }
+        if let Some(networks) = &mut self.networks {
+            networks.refresh();
+        }
+        if let Some(disk_counter) = &mut self.disk_counter {
+            disk_counter.update();
+        }
+        if let Some(thread_counter) = &mut self.thread_counter {
+            thread_counter.update(self.last_duration);
+        }
 
         #[cfg(target_os = "macos")]
-        if let Some(command_source) = &mut self.command_source {
-            if self.features.contains(Features::CPU_FREQUENCY) {
-                command_source.update_power_metrics_data();
+        let r = match &mut self.command_source {
+            Some(command_source)
+                if self
+                    .features
+                    .intersects(Features::PROCESS_GPU | Features::CPU_FREQUENCY) =>
+            {
+                command_source.update_power_metrics_data()
             }
+            _ => Ok(()),
+        };
+        #[cfg(not(target_os = "macos"))]
+        let r = Ok(());
+
+        #[cfg(target_os = "macos")]
+        if let Some(command_source) = &mut self.command_source {
             if self.features.contains(Features::NET_TRAFFIC)
                 | self.features.contains(Features::FPS)
                 | self.features.contains(Features::K_OBJECT)
@@ -208,8 +359,13 @@ impl System {
         }
 
         #[cfg(target_os = "macos")]
-        if let Some(ioreg) = &mut self.ioreg {
-            ioreg.update();
+        {
+            if let Some(ioreg) = &mut self.ioreg {
+                ioreg.update();
+            }
+            if let Some(energy_counter) = &mut self.energy_counter {
+                energy_counter.update();
+            }
         }
 
         #[cfg(target_os = "windows")]
@@ -224,19 +380,236 @@ impl System {
                 vm_counter.update();
             }
         }
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(proc_counter) = &mut self.proc_counter {
+                proc_counter.update();
+            }
+            if self.features.contains(Features::GPU) {
+                if let Some(drm_counter) = &mut self.drm_counter {
+                    drm_counter.update();
+                }
+            }
+            if let Some(energy_meter) = &mut self.energy_meter {
+                energy_meter.update();
+            }
+            if let Some(frame_tracer) = &mut self.frame_tracer {
+                frame_tracer.update();
+            }
+        }
+
+        r
+    }
+
+    // Time between the last two updates, rates are computed over it
+    pub fn last_sample_duration(&self) -> Duration {
+        self.last_duration
+    }
+
+    pub fn add_pids<T: IntoIterator<Item = Pid>>(&mut self, pids: T) {
+        let pids: Vec<_> = pids
+            .into_iter()
+            .filter(|pid| !self.pids.contains(pid))
+            .collect();
+        if pids.is_empty() {
+            return;
+        }
+
+        if let Some(thread_counter) = &mut self.thread_counter {
+            thread_counter.add_pids(pids.iter().copied());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            if let Some(command_source) = &mut self.command_source {
+                command_source.add_pids(pids.iter().copied());
+            }
+            if let Some(energy_counter) = &mut self.energy_counter {
+                energy_counter.add_pids(pids.iter().copied());
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        if let Some(vm_counter) = &mut self.vm_counter {
+            vm_counter.add_pids(pids.iter().copied());
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(proc_counter) = &mut self.proc_counter {
+                proc_counter.add_pids(pids.iter().copied());
+            }
+            if let Some(drm_counter) = &mut self.drm_counter {
+                drm_counter.add_pids(pids.iter().copied());
+            }
+        }
+
+        self.pids.extend(pids);
+    }
+
+    pub fn remove_pids<T: IntoIterator<Item = Pid>>(&mut self, pids: T) {
+        let pids: Vec<_> = pids.into_iter().collect();
+
+        if let Some(thread_counter) = &mut self.thread_counter {
+            thread_counter.remove_pids(&pids);
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            if let Some(command_source) = &mut self.command_source {
+                command_source.remove_pids(&pids);
+            }
+            if let Some(energy_counter) = &mut self.energy_counter {
+                energy_counter.remove_pids(&pids);
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        if let Some(vm_counter) = &mut self.vm_counter {
+            vm_counter.remove_pids(&pids);
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(proc_counter) = &mut self.proc_counter {
+                proc_counter.remove_pids(&pids);
+            }
+            if let Some(drm_counter) = &mut self.drm_counter {
+                drm_counter.remove_pids(&pids);
+            }
+        }
+
+        self.pids.retain(|pid| !pids.contains(pid));
+    }
+
+    // Sample the categories of every pid in one call
+    pub fn sample(&mut self, categories: &[ProcessCategory]) -> Vec<ProcessSample> {
+        let pids = self.pids.clone();
+        pids.into_iter()
+            .map(|pid| self.sample_process(pid, categories))
+            .collect()
+    }
+
+    pub fn sample_process(&mut self, pid: Pid, categories: &[ProcessCategory]) -> ProcessSample {
+        let mut sample = ProcessSample {
+            pid,
+            ..Default::default()
+        };
+
+        for &c in categories {
+            match c {
+                ProcessCategory::Cpu => sample.cpu_usage = self.process_cpu_usage(pid),
+                ProcessCategory::Mem => sample.mem = self.process_mem(pid),
+                ProcessCategory::Alloc => sample.alloc = self.process_alloc(pid),
+                ProcessCategory::Gpu(calc) => sample.gpu_usage = self.process_gpu_usage(pid, calc),
+                ProcessCategory::Vram(calc) => sample.vram = self.process_vram(pid, calc),
+                ProcessCategory::VramShared(calc) => {
+                    sample.vram_shared = self.process_vram_shared(pid, calc)
+                }
+                ProcessCategory::Fps => sample.fps = self.process_fps(pid),
+                ProcessCategory::NetIn => sample.net_traffic_in = self.process_net_traffic_in(pid),
+                ProcessCategory::NetOut => {
+                    sample.net_traffic_out = self.process_net_traffic_out(pid)
+                }
+                ProcessCategory::DiskRead => sample.disk_read = self.process_disk_read(pid),
+                ProcessCategory::DiskWrite => sample.disk_write = self.process_disk_write(pid),
+                ProcessCategory::DiskReadOps => {
+                    sample.disk_read_ops = self.process_disk_read_ops(pid)
+                }
+                ProcessCategory::DiskWriteOps => {
+                    sample.disk_write_ops = self.process_disk_write_ops(pid)
+                }
+                ProcessCategory::Kobject => sample.kobject = self.process_kobject(pid),
+                ProcessCategory::MachPorts => sample.mach_ports = self.process_mach_ports(pid),
+                ProcessCategory::Fds => sample.fds = self.process_fds(pid),
+                ProcessCategory::Sockets => sample.sockets = self.process_sockets(pid),
+                ProcessCategory::Thread => sample.threads = self.process_threads(pid),
+                ProcessCategory::PageFaults => sample.page_faults = self.process_page_faults(pid),
+                ProcessCategory::CtxSwitches => {
+                    sample.ctx_switches = self.process_ctx_switches(pid)
+                }
+                ProcessCategory::IoWait => sample.io_wait = self.process_io_wait(pid),
+                ProcessCategory::Power => sample.power = self.process_power(pid),
+                ProcessCategory::Uptime => sample.uptime = self.process_uptime(pid),
+            }
+        }
+
+        sample
+    }
+
+    // Features producing data on this machine, `System::new` may still accept the others but they
+    // stay empty, e.g., FPS without SIP allowing dtrace on macOS
+    pub fn supported_features() -> Features {
+        let mut features = Features::all();
+
+        #[cfg(target_os = "macos")]
+        {
+            // powermetrics refuses to run without root
+            if unsafe { libc::geteuid() } != 0 {
+                features.remove(Features::PROCESS_GPU | Features::CPU_FREQUENCY);
+            }
+            if !platform::macos::frame_rate_available() {
+                features.remove(Features::FPS);
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            features.remove(Features::ENERGY);
+            if !platform::windows::is_elevated() {
+                features.remove(Features::FPS | Features::NET_TRAFFIC);
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            features.remove(Features::NET_TRAFFIC | Features::ENERGY);
+            if !FrameTracer::available() {
+                features.remove(Features::FPS);
+            }
+        }
+
+        features
+    }
+
+    // `System` isn't `Send` on Windows, the sampler creates it on its own thread and sends the samples
+    // of `categories` every `interval`, e.g., to keep sampling off the UI thread
+    pub fn spawn_sampler<T>(
+        features: Features,
+        pids: T,
+        categories: Vec<ProcessCategory>,
+        interval: Duration,
+    ) -> Result<Sampler, Error>
+    where
+        T: IntoIterator<Item = Pid> + Clone + Send + 'static,
+    {
+        Sampler::spawn(features, pids, categories, interval)
     }
 
     pub fn sysinfo_system(&self) -> Option<&sysinfo::System> {
         self.sysinfo_system.as_ref()
     }
 
+    pub fn set_cpu_scale(&mut self, cpu_scale: CpuScale) {
+        self.cpu_scale = cpu_scale;
+    }
+
     pub fn process_cpu_usage(&self, pid: Pid) -> Option<f32> {
-        Some(
-            self.sysinfo_system
-                .as_ref()?
-                .process(sysinfo::Pid::from_u32(pid))?
-                .cpu_usage(),
-        )
+        let sysinfo_system = self.sysinfo_system.as_ref()?;
+        let usage = sysinfo_system
+            .process(sysinfo::Pid::from_u32(pid))?
+            .cpu_usage();
+        match self.cpu_scale {
+            CpuScale::PerCore => Some(usage),
+            CpuScale::Normalized => {
+                let cores = sysinfo_system
+                    .physical_core_count()
+                    .unwrap_or(sysinfo_system.cpus().len())
+                    .max(1);
+                Some(usage / cores as f32)
+            }
+        }
     }
 
     pub fn process_mem(&mut self, pid: Pid) -> Option<usize> {
@@ -301,16 +674,12 @@ impl System {
         }
     }
 
+    // Mach ports and fds on macOS, handles on Windows, fds on Linux
     pub fn process_kobject(&mut self, pid: Pid) -> Option<u32> {
         #[cfg(target_os = "macos")]
         {
-            let mach_ports = self
-                .command_source
-                .as_ref()
-                .map(|c| c.process_mach_ports(pid))
-                .flatten()
-                .unwrap_or_default();
-            let fds = platform::macos::proc_fds(pid).unwrap_or_default();
+            let mach_ports = self.process_mach_ports(pid).unwrap_or_default();
+            let fds = self.process_fds(pid).unwrap_or_default();
             Some(mach_ports + fds)
         }
 
@@ -320,12 +689,65 @@ impl System {
         }
 
         #[cfg(target_os = "linux")]
+        {
+            platform::linux::proc_fds(pid)
+        }
+    }
+
+    // Sampled by top with `Features::K_OBJECT`
+    pub fn process_mach_ports(&self, pid: Pid) -> Option<u32> {
+        #[cfg(target_os = "macos")]
+        {
+            self.command_source.as_ref()?.process_mach_ports(pid)
+        }
+
+        #[cfg(not(target_os = "macos"))]
         {
             let _ = pid;
             None
         }
     }
 
+    pub fn process_fds(&self, pid: Pid) -> Option<u32> {
+        #[cfg(target_os = "macos")]
+        {
+            platform::macos::proc_fds(pid)
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            let _ = pid;
+            None
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            platform::linux::proc_fds(pid)
+        }
+    }
+
+    pub fn process_sockets(&self, pid: Pid) -> Option<u32> {
+        #[cfg(target_os = "macos")]
+        {
+            platform::macos::proc_sockets(pid)
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            platform::windows::process_sockets(pid)
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            platform::linux::proc_sockets(pid)
+        }
+    }
+
+    // Usage of every thread over the last update with `Features::THREADS`
+    pub fn process_threads_usage(&self, pid: Pid) -> Option<Vec<ThreadUsage>> {
+        Some(self.thread_counter.as_ref()?.usage(pid)?.to_vec())
+    }
+
     pub fn process_threads(&self, pid: Pid) -> Option<u32> {
         platform::threads_count(pid)
     }
@@ -339,20 +761,19 @@ impl System {
                 .process(sysinfo::Pid::from_u32(pid))?
                 .disk_usage()
                 .read_bytes;
-            Some(read_bytes as f32 / self.last_duration.as_secs_f32())
+            Some(read_bytes as f32 / rate_secs(self.last_duration))
         }
 
         #[cfg(target_os = "windows")]
         {
-            // TODO
-            let _ = pid;
-            None
+            let read_bytes = self.vm_counter.as_ref()?.process_read_bytes(pid)?;
+            Some(read_bytes as f32 / rate_secs(self.last_duration))
         }
 
         #[cfg(target_os = "linux")]
         {
-            let _ = pid;
-            None
+            let read_bytes = self.proc_counter.as_ref()?.process_read_bytes(pid)?;
+            Some(read_bytes as f32 / rate_secs(self.last_duration))
         }
     }
 
@@ -365,23 +786,177 @@ impl System {
                 .process(sysinfo::Pid::from_u32(pid))?
                 .disk_usage()
                 .written_bytes;
-            Some(written_bytes as f32 / self.last_duration.as_secs_f32())
+            Some(written_bytes as f32 / rate_secs(self.last_duration))
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            let write_bytes = self.vm_counter.as_ref()?.process_write_bytes(pid)?;
+            Some(write_bytes as f32 / rate_secs(self.last_duration))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let write_bytes = self.proc_counter.as_ref()?.process_write_bytes(pid)?;
+            Some(write_bytes as f32 / rate_secs(self.last_duration))
+        }
+    }
+
+    // Read operations per second, no source on macOS
+    pub fn process_disk_read_ops(&self, pid: Pid) -> Option<f32> {
+        #[cfg(target_os = "macos")]
+        {
+            let _ = pid;
+            None
         }
 
         #[cfg(target_os = "windows")]
         {
-            // TODO
+            let read_ops = self.vm_counter.as_ref()?.process_read_ops(pid)?;
+            Some(read_ops as f32 / rate_secs(self.last_duration))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let read_ops = self.proc_counter.as_ref()?.process_read_ops(pid)?;
+            Some(read_ops as f32 / rate_secs(self.last_duration))
+        }
+    }
+
+    // Write operations per second, no source on macOS
+    pub fn process_disk_write_ops(&self, pid: Pid) -> Option<f32> {
+        #[cfg(target_os = "macos")]
+        {
             let _ = pid;
             None
         }
 
+        #[cfg(target_os = "windows")]
+        {
+            let write_ops = self.vm_counter.as_ref()?.process_write_ops(pid)?;
+            Some(write_ops as f32 / rate_secs(self.last_duration))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let write_ops = self.proc_counter.as_ref()?.process_write_ops(pid)?;
+            Some(write_ops as f32 / rate_secs(self.last_duration))
+        }
+    }
+
+    pub fn process_page_faults(&self, pid: Pid) -> Option<f32> {
+        #[cfg(target_os = "macos")]
+        {
+            let _ = pid;
+            None
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            let page_faults = self.vm_counter.as_ref()?.process_page_faults(pid)?;
+            Some(page_faults as f32 / rate_secs(self.last_duration))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let page_faults = self.proc_counter.as_ref()?.process_page_faults(pid)?;
+            Some(page_faults as f32 / rate_secs(self.last_duration))
+        }
+    }
+
+    pub fn process_ctx_switches(&self, pid: Pid) -> Option<f32> {
+        #[cfg(target_os = "macos")]
+        {
+            let _ = pid;
+            None
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            let ctx_switches = self.vm_counter.as_ref()?.process_ctx_switches(pid)?;
+            Some(ctx_switches as f32 / rate_secs(self.last_duration))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let ctx_switches = self.proc_counter.as_ref()?.process_ctx_switches(pid)?;
+            Some(ctx_switches as f32 / rate_secs(self.last_duration))
+        }
+    }
+
+    // % of the interval spent blocked on I/O, Linux with delay accounting only
+    pub fn process_io_wait(&self, pid: Pid) -> Option<f32> {
         #[cfg(target_os = "linux")]
+        {
+            let io_wait = self.proc_counter.as_ref()?.process_io_wait(pid)?;
+            Some(io_wait.as_secs_f32() / rate_secs(self.last_duration) * 100.)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = pid;
+            None
+        }
+    }
+
+    // mW
+    pub fn process_power(&self, pid: Pid) -> Option<f32> {
+        #[cfg(target_os = "macos")]
+        {
+            self.energy_counter
+                .as_ref()?
+                .process_power(pid, self.last_duration)
+        }
+
+        #[cfg(not(target_os = "macos"))]
         {
             let _ = pid;
             None
         }
     }
 
+    // Whether a process with the pid is running, `Error::AccessDenied` when it is but can't be queried
+    pub fn process_exists(pid: Pid) -> Result<bool, Error> {
+        #[cfg(target_os = "windows")]
+        {
+            platform::windows::process_exists(pid)
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            if unsafe { libc::kill(pid as _, 0) } == 0 {
+                return Ok(true);
+            }
+            match std::io::Error::last_os_error().raw_os_error() {
+                Some(libc::EPERM) => Err(Error::AccessDenied),
+                _ => Ok(false),
+            }
+        }
+    }
+
+    // Every process of the system, empty without `Features::PROCESS`
+    pub fn list_processes(&self) -> Vec<ProcessBrief> {
+        let Some(sysinfo_system) = self
+            .sysinfo_system
+            .as_ref()
+            .filter(|_| self.features.contains(Features::PROCESS))
+        else {
+            return vec![];
+        };
+
+        sysinfo_system
+            .processes()
+            .iter()
+            .map(|(&pid, p)| ProcessBrief {
+                pid: pid.as_u32(),
+                parent: p.parent().map(|parent| parent.as_u32()),
+                name: p.name().to_string(),
+                cmd: p.cmd().to_vec(),
+                status: p.status().into(),
+            })
+            .collect()
+    }
+
     pub fn process_name(&self, pid: Pid) -> Option<&str> {
         Some(
             self.sysinfo_system
@@ -391,6 +966,36 @@ impl System {
         )
     }
 
+    // Full path of the executable, tells apart processes with the same name
+    pub fn process_exe(&self, pid: Pid) -> Option<PathBuf> {
+        Some(
+            self.sysinfo_system
+                .as_ref()?
+                .process(sysinfo::Pid::from_u32(pid))?
+                .exe()?
+                .to_path_buf(),
+        )
+    }
+
+    // Seconds since the Unix epoch
+    pub fn process_start_time(&self, pid: Pid) -> Option<u64> {
+        Some(
+            self.sysinfo_system
+                .as_ref()?
+                .process(sysinfo::Pid::from_u32(pid))?
+                .start_time(),
+        )
+    }
+
+    pub fn process_uptime(&self, pid: Pid) -> Option<Duration> {
+        let start_time = UNIX_EPOCH + Duration::from_secs(self.process_start_time(pid)?);
+        Some(
+            SystemTime::now()
+                .duration_since(start_time)
+                .unwrap_or_default(),
+        )
+    }
+
     pub fn process_command(&self, pid: Pid) -> Option<&[String]> {
         Some(
             self.sysinfo_system
@@ -419,12 +1024,11 @@ impl System {
 
         #[cfg(target_os = "linux")]
         {
-            let _ = pid;
-            None
+            platform::linux::proc_responsible(pid)
         }
     }
 
-    pub fn system_cpu_frequency(&self) -> Result<Vec<f32>, Error> {
+    pub fn system_cpu_frequency(&self) -> Result<Vec<CpuFrequency>, Error> {
         #[cfg(target_os = "macos")]
         {
             Ok(self
@@ -439,7 +1043,13 @@ impl System {
 ")?;
             Ok(processor_info
                 .into_iter()
-                .map(|p| p.processor_frequency * p.percent_processor_performance / 100.0)
+                .map(|p| CpuFrequency {
+                    group: p
+                        .name
+                        .split_once(',')
+                        .map(|(node, _)| format!("Node{}", node)),
+                    frequency: p.processor_frequency * p.percent_processor_performance / 100.0,
+                })
                 .collect())
         }
 
@@ -450,8 +1060,12 @@ impl System {
                 .as_ref()
                 .ok_or(Error::FeatureMissing(Features::CPU_FREQUENCY))?
                 .cpus()
-                .into_iter()
-                .map(|cpu| cpu.frequency() as f32)
+                .iter()
+                .enumerate()
+                .map(|(i, cpu)| CpuFrequency {
+                    group: platform::linux::cpu_numa_node(i).map(|node| format!("Node{}", node)),
+                    frequency: cpu.frequency() as f32,
+                })
                 .collect())
         }
     }
@@ -459,8 +1073,8 @@ impl System {
     pub fn process_gpu_usage(&mut self, pid: Pid, calc: GpuCalculation) -> Option<f32> {
         #[cfg(target_os = "macos")]
         {
-            let _ = (pid, calc);
-            Some(0.0)
+            let _ = calc;
+            self.command_source.as_ref()?.process_gpu_usage(pid)
         }
 
         #[cfg(target_os = "windows")]
@@ -474,16 +1088,18 @@ impl System {
 
         #[cfg(target_os = "linux")]
         {
-            let _ = (pid, calc);
-            None
+            self.drm_counter
+                .as_ref()?
+                .process_gpu_usage(pid, calc, self.last_duration)
         }
     }
 
     pub fn process_vram(&mut self, pid: Pid, calc: GpuCalculation) -> Option<f32> {
         #[cfg(target_os = "macos")]
         {
-            let _ = (pid, calc);
-            None
+            // The task ledger accounts all accelerators together
+            let _ = calc;
+            platform::macos::graphics_footprint(pid).map(|v| v as f32)
         }
 
         #[cfg(target_os = "windows")]
@@ -502,25 +1118,43 @@ impl System {
         }
     }
 
-    pub fn process_fps(&mut self, pid: Pid) -> f32 {
+    // Shared GPU memory, i.e., system memory used by the GPU, which dominates on integrated GPUs
+    pub fn process_vram_shared(&mut self, pid: Pid, calc: GpuCalculation) -> Option<f32> {
+        #[cfg(target_os = "windows")]
+        {
+            self.pdh.as_mut().unwrap().poll_gpu_usage(
+                platform::windows::GpuCounterType::VRamShared,
+                Some(pid),
+                calc,
+            )
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            let _ = (pid, calc);
+            None
+        }
+    }
+
+    pub fn process_fps(&mut self, pid: Pid) -> Option<f32> {
         #[cfg(target_os = "macos")]
         {
-            self.command_source
-                .as_ref()
-                .unwrap()
-                .process_frame_per_sec(pid)
-                .unwrap_or(0.0)
+            Some(
+                self.command_source
+                    .as_ref()?
+                    .process_frame_per_sec(pid)
+                    .unwrap_or(0.0),
+            )
         }
 
         #[cfg(target_os = "windows")]
         {
-            self.etw_trace.as_mut().unwrap().fps(pid)
+            Some(self.etw_trace.as_mut()?.fps(pid))
         }
 
         #[cfg(target_os = "linux")]
         {
-            let _ = pid;
-            0.
+            Some(self.frame_tracer.as_ref()?.fps(pid))
         }
     }
 
@@ -565,25 +1199,83 @@ impl System {
             .ok_or(Error::FeatureMissing(Features::PROCESS))?;
         Ok(sysinfo_system
             .cpus()
-            .into_iter()
+            .iter()
             .map(|cpu| cpu.cpu_usage())
             .collect())
     }
 
-    pub fn system_gpu_usage(&mut self, calc: GpuCalculation) -> Option<f32> {
+    // Usage of all cpus together, 100% is the whole machine
+    pub fn system_cpu_total_usage(&self) -> Result<f32, Error> {
+        let sysinfo_system = self
+            .sysinfo_system
+            .as_ref()
+            .ok_or(Error::FeatureMissing(Features::PROCESS))?;
+        Ok(sysinfo_system.global_cpu_info().cpu_usage())
+    }
+
+    pub fn system_memory_used(&self) -> Result<u64, Error> {
+        let sysinfo_system = self
+            .sysinfo_system
+            .as_ref()
+            .ok_or(Error::FeatureMissing(Features::PROCESS))?;
+        Ok(sysinfo_system.used_memory())
+    }
+
+    pub fn system_swap_used(&self) -> Result<u64, Error> {
+        let sysinfo_system = self
+            .sysinfo_system
+            .as_ref()
+            .ok_or(Error::FeatureMissing(Features::PROCESS))?;
+        Ok(sysinfo_system.used_swap())
+    }
+
+    // Bytes per second received and transmitted by all interfaces
+    pub fn system_net_traffic(&self) -> Result<(f32, f32), Error> {
+        let networks = self
+            .networks
+            .as_ref()
+            .ok_or(Error::FeatureMissing(Features::NETWORK))?;
+        let secs = rate_secs(self.last_duration);
+        let (received, transmitted) = networks.iter().fold((0, 0), |(r, t), (_, data)| {
+            (r + data.received(), t + data.transmitted())
+        });
+        Ok((received as f32 / secs, transmitted as f32 / secs))
+    }
+
+    // Bytes per second read and written by every physical disk
+    pub fn system_disk_io(&self) -> Result<Vec<DiskIo>, Error> {
+        let disk_counter = self
+            .disk_counter
+            .as_ref()
+            .ok_or(Error::UnsupportedFeatures(Features::DISK))?;
+        #[cfg(not(target_os = "windows"))]
+        {
+            Ok(disk_counter.disks(self.last_duration))
+        }
+        #[cfg(target_os = "windows")]
+        {
+            Ok(disk_counter.disks())
+        }
+    }
+
+    // One reading per adapter
+    pub fn system_gpu_usage(&mut self, calc: GpuCalculation) -> Option<Vec<f32>> {
         #[cfg(target_os = "macos")]
         {
             let _ = calc;
-            Some(self.ioreg.as_ref().unwrap().sys_gpu_usage())
+            Some(
+                self.ioreg
+                    .as_ref()?
+                    .gpu_devices()
+                    .iter()
+                    .map(|d| d.usage)
+                    .collect(),
+            )
         }
 
         #[cfg(target_os = "windows")]
         {
-            self.pdh.as_mut().unwrap().poll_gpu_usage(
-                platform::windows::GpuCounterType::Utilization,
-                None,
-                calc,
-            )
+            self.pdh.as_mut()?.poll_adapters_gpu_usage(calc)
         }
 
         #[cfg(target_os = "linux")]
@@ -593,6 +1285,35 @@ impl System {
         }
     }
 
+    // Utilization of every engine type, e.g., 3D or VideoEncode, in the order they were first seen
+    pub fn system_gpu_engines(&mut self, calc: GpuCalculation) -> Option<Vec<(String, f32)>> {
+        #[cfg(target_os = "windows")]
+        {
+            self.pdh.as_mut()?.poll_engines_gpu_usage(calc)
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            let _ = calc;
+            None
+        }
+    }
+
+    // Per adapter readings of `system_gpu_usage`
+    pub fn system_gpu_devices(&self) -> Result<Vec<GpuDevice>, Error> {
+        #[cfg(target_os = "macos")]
+        {
+            Ok(self
+                .ioreg
+                .as_ref()
+                .ok_or(Error::FeatureMissing(Features::GPU))?
+                .gpu_devices())
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        Err(Error::UnsupportedFeatures(Features::GPU))
+    }
+
     pub fn system_cpu_temperature(&mut self) -> Result<Vec<f32>, Error> {
         #[cfg(target_os = "macos")]
         {
@@ -621,12 +1342,18 @@ impl System {
         }
         #[cfg(target_os = "windows")]
         {
-            Err(Error::UnsupportedFeatures(Features::SMC))
+            // No rows without WinRing0x64.dll rather than an error, it's shipped by users
+            Ok(self
+                .winring0
+                .as_ref()
+                .and_then(|winring0| winring0.package_temperature())
+                .into_iter()
+                .collect())
         }
 
         #[cfg(target_os = "linux")]
         {
-            Err(Error::UnsupportedFeatures(Features::SMC))
+            Ok(platform::linux::hwmon_cpu_temperature())
         }
     }
 
@@ -650,7 +1377,36 @@ impl System {
 
         #[cfg(target_os = "linux")]
         {
-            Err(Error::UnsupportedFeatures(Features::SMC))
+            let energy_meter = self
+                .energy_meter
+                .as_ref()
+                .ok_or(Error::UnsupportedFeatures(Features::SMC))?;
+            Ok(energy_meter.power(self.last_duration))
+        }
+    }
+
+    // %
+    pub fn system_battery_percent(&self) -> Result<f32, Error> {
+        #[cfg(target_os = "macos")]
+        {
+            let smc = self
+                .smc
+                .as_ref()
+                .ok_or(Error::FeatureMissing(Features::SMC))?;
+            Ok(smc.read_key::<u16>("BRSC".into())? as f32)
+        }
+        #[cfg(target_os = "windows")]
+        {
+            let battery = self
+                .battery
+                .as_ref()
+                .ok_or(Error::UnsupportedFeatures(Features::SMC))?;
+            battery.percent()
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            platform::linux::battery_percent().ok_or(Error::UnsupportedFeatures(Features::SMC))
         }
     }
 
@@ -679,5 +1435,11 @@ bitflags! {
         const SMC =             1 << 4;
         const NET_TRAFFIC =     1 << 5;
         const K_OBJECT =        1 << 6;
+        const ENERGY =          1 << 7;
+        const NETWORK =         1 << 8;
+        const DISK =            1 << 9;
+        const THREADS =         1 << 10;
+        // GPU usage of processes, from powermetrics on macOS
+        const PROCESS_GPU =     1 << 11;
     }
 }
@@ -0,0 +1,70 @@
+use crate::{platform, rate_secs, Pid, ThreadTime, ThreadUsage};
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Usage of every thread of the pids from the cpu time it used between two updates
+pub(crate) struct ThreadCounter {
+    last_times: HashMap<Pid, HashMap<u64, Duration>>,
+    usage: HashMap<Pid, Vec<ThreadUsage>>,
+}
+
+impl ThreadCounter {
+    pub fn new<T: IntoIterator<Item = Pid>>(pids: T) -> Self {
+        let mut counter = Self {
+            last_times: HashMap::new(),
+            usage: HashMap::new(),
+        };
+        counter.add_pids(pids);
+        counter
+    }
+
+    pub fn add_pids<T: IntoIterator<Item = Pid>>(&mut self, pids: T) {
+        for pid in pids {
+            if let Some(times) = platform::thread_times(pid) {
+                self.last_times.insert(pid, cpu_times(&times));
+            }
+        }
+    }
+
+    pub fn remove_pids(&mut self, pids: &[Pid]) {
+        self.last_times.retain(|pid, _| !pids.contains(pid));
+        self.usage.retain(|pid, _| !pids.contains(pid));
+    }
+
+    pub fn update(&mut self, duration: Duration) {
+        let secs = rate_secs(duration);
+        let pids: Vec<_> = self.last_times.keys().copied().collect();
+
+        for pid in pids {
+            let Some(times) = platform::thread_times(pid) else {
+                self.usage.remove(&pid);
+                continue;
+            };
+
+            // Threads started since the last update have no usage yet
+            let last = &self.last_times[&pid];
+            let usage = times
+                .iter()
+                .map(|t| ThreadUsage {
+                    id: t.id,
+                    name: t.name.clone(),
+                    cpu_usage: match last.get(&t.id) {
+                        Some(&last) => 100. * t.cpu_time.saturating_sub(last).as_secs_f32() / secs,
+                        _ => 0.,
+                    },
+                })
+                .collect();
+
+            self.usage.insert(pid, usage);
+            self.last_times.insert(pid, cpu_times(&times));
+        }
+    }
+
+    pub fn usage(&self, pid: Pid) -> Option<&[ThreadUsage]> {
+        self.usage.get(&pid).map(|u| u.as_slice())
+    }
+}
+
+fn cpu_times(times: &[ThreadTime]) -> HashMap<u64, Duration> {
+    times.iter().map(|t| (t.id, t.cpu_time)).collect()
+}
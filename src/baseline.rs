@@ -0,0 +1,71 @@
+use crate::merge::Capture;
+use crate::opt::{ProcessCategory, SystemCategory};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Means of an idle `.json` capture, subtracted from live samples by `--baseline`
+pub struct Baseline {
+    process: HashMap<ProcessCategory, f32>,
+    system: HashMap<SystemCategory, Vec<f32>>,
+}
+
+impl Baseline {
+    // Means are turned into the units samples are taken in, i.e., bytes with `--raw-units`
+    pub fn read(path: &Path, raw_units: bool) -> Result<Self, String> {
+        let capture = Capture::read(path)?;
+        let len = capture.len();
+        if len == 0 {
+            return Err("no samples".to_string());
+        }
+
+        // Processes of the capture are averaged together, live processes needn't be the same ones
+        let process = capture
+            .process_records
+            .iter()
+            .filter(|(_, records)| !records.is_empty())
+            .map(|(&c, records)| {
+                let sum: f32 = records.iter().map(|r| mean(&r.values(len))).sum();
+                let (_, scale) = c.output_unit(raw_units);
+                (c, sum / records.len() as f32 * scale)
+            })
+            .collect();
+        let system = capture
+            .sys_records
+            .iter()
+            .map(|(&c, records)| {
+                let (_, scale) = c.output_unit(raw_units);
+                (
+                    c,
+                    records
+                        .iter()
+                        .map(|r| mean(&r.values(len)) * scale)
+                        .collect(),
+                )
+            })
+            .collect();
+
+        Ok(Self { process, system })
+    }
+
+    pub fn process(&self, c: ProcessCategory, v: f32) -> f32 {
+        (v - self.process.get(&c).copied().unwrap_or(0.)).max(0.)
+    }
+
+    // Rows are matched by index, e.g., the cores of `sys_cpu`
+    pub fn system(&self, c: SystemCategory, rows: &mut [f32]) {
+        let Some(means) = self.system.get(&c) else {
+            return;
+        };
+        for (v, m) in rows.iter_mut().zip(means) {
+            *v = (*v - m).max(0.);
+        }
+    }
+}
+
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        0.
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+}
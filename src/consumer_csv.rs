@@ -1,7 +1,20 @@
-use crate::opt::{ProcessCategory, SystemCategory};
+use crate::opt::{CsvLayout, ProcessCategory, SystemCategory, TimestampFormat};
 use crate::types::{ProcessInfo, SystemMetrics};
+use std::fs::OpenOptions;
+use std::io::Write;
 use std::path::Path;
 
+pub struct Options {
+    // Existing content is kept and the title row is skipped, the long layout only
+    pub append: bool,
+    pub layout: CsvLayout,
+    pub timestamp: TimestampFormat,
+    // Decimal places of every category instead of its own
+    pub precision: Option<usize>,
+    // Values are sampled as bytes instead of M and KBps
+    pub raw_units: bool,
+}
+
 pub fn consume<P: AsRef<Path>>(
     path: P,
     proc_categories: &[ProcessCategory],
@@ -9,14 +22,38 @@ pub fn consume<P: AsRef<Path>>(
     timestamps: &[chrono::DateTime<chrono::Local>],
     processes: &[ProcessInfo],
     system_metrics: &[SystemMetrics],
+    options: Options,
 ) {
-    let mut wtr = csv::WriterBuilder::new()
-        .flexible(true)
-        .from_path(path)
+    let append = options.append;
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
         .unwrap();
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(file);
+
+    if let CsvLayout::Long = options.layout {
+        consume_long(
+            &mut wtr,
+            proc_categories,
+            sys_categories,
+            timestamps,
+            processes,
+            system_metrics,
+            &options,
+        );
+        wtr.get_ref().sync_all().unwrap();
+        return;
+    }
 
     // Process
-    for (ci, &c) in proc_categories.into_iter().enumerate() {
+    for (ci, &c) in proc_categories.iter().enumerate() {
+        let (_, scale) = c.output_unit(options.raw_units);
+        let precision = options
+            .precision
+            .unwrap_or(output_precision(c.precision(), scale));
         // Title
         wtr.write_field(format!("Process {:?}", c)).unwrap();
         for p in processes {
@@ -25,12 +62,14 @@ pub fn consume<P: AsRef<Path>>(
         wtr.write_record(None::<&[u8]>).unwrap();
 
         // Data
-        for (i, t) in timestamps.into_iter().enumerate() {
+        for (i, t) in timestamps.iter().enumerate() {
             // Timestamp
-            wtr.write_field(t.to_rfc3339()).unwrap();
+            wtr.write_field(options.timestamp.format(t).to_string())
+                .unwrap();
             // Process data
             for p in processes {
-                wtr.write_field(format!("{:.2}", p.values[ci][i])).unwrap();
+                wtr.write_field(format!("{:.*}", precision, p.values[ci][i]))
+                    .unwrap();
             }
             wtr.write_record(None::<&[u8]>).unwrap();
         }
@@ -39,27 +78,190 @@ pub fn consume<P: AsRef<Path>>(
     }
 
     // System
-    for (i, &c) in sys_categories.into_iter().enumerate() {
+    for (i, &c) in sys_categories.iter().enumerate() {
+        let (_, scale) = c.output_unit(options.raw_units);
+        let precision = options
+            .precision
+            .unwrap_or(output_precision(c.precision(), scale));
         let metrics = &system_metrics[i];
 
         // Title
         wtr.write_field(format!("System {:?}", c)).unwrap();
         for i in 0..metrics.rows.len() {
-            wtr.write_field(format!("{:?}{}", c, i)).unwrap();
+            wtr.write_field(metrics.row_label(c, i)).unwrap();
         }
         wtr.write_record(None::<&[u8]>).unwrap();
 
         // Data
-        for (i, t) in timestamps.into_iter().enumerate() {
+        for (i, t) in timestamps.iter().enumerate() {
             // Timestamp
-            wtr.write_field(t.to_string()).unwrap();
+            wtr.write_field(options.timestamp.format(t).to_string())
+                .unwrap();
             // Process data
             for row in metrics.rows.iter() {
-                wtr.write_field(format!("{:.2}", row[i])).unwrap();
+                wtr.write_field(format!("{:.*}", precision, row[i]))
+                    .unwrap();
             }
             wtr.write_record(None::<&[u8]>).unwrap();
         }
 
         wtr.write_record([" "]).unwrap();
     }
+
+    wtr.flush().unwrap();
+    wtr.get_ref().sync_all().unwrap();
+}
+
+// A row per value, for tools grouping by columns
+fn consume_long<W: Write>(
+    wtr: &mut csv::Writer<W>,
+    proc_categories: &[ProcessCategory],
+    sys_categories: &[SystemCategory],
+    timestamps: &[chrono::DateTime<chrono::Local>],
+    processes: &[ProcessInfo],
+    system_metrics: &[SystemMetrics],
+    options: &Options,
+) {
+    if !options.append {
+        wtr.write_record([
+            "timestamp",
+            "kind",
+            "category",
+            "pid",
+            "name",
+            "row_index",
+            "value",
+        ])
+        .unwrap();
+    }
+
+    let timestamps: Vec<_> = timestamps
+        .iter()
+        .map(|t| options.timestamp.format(t).to_string())
+        .collect();
+
+    // Process
+    for (ci, &c) in proc_categories.iter().enumerate() {
+        let (_, scale) = c.output_unit(options.raw_units);
+        let precision = options
+            .precision
+            .unwrap_or(output_precision(c.precision(), scale));
+        let category = format!("{:?}", c);
+        for p in processes {
+            let pid = p.pid.to_string();
+            for (t, v) in timestamps.iter().zip(&p.values[ci]) {
+                wtr.write_record([
+                    t,
+                    "process",
+                    &category,
+                    &pid,
+                    &p.name,
+                    "",
+                    &format!("{:.*}", precision, v),
+                ])
+                .unwrap();
+            }
+        }
+    }
+
+    // System
+    for (i, &c) in sys_categories.iter().enumerate() {
+        let (_, scale) = c.output_unit(options.raw_units);
+        let precision = options
+            .precision
+            .unwrap_or(output_precision(c.precision(), scale));
+        let metrics = &system_metrics[i];
+        let category = format!("{:?}", c);
+        for (ri, row) in metrics.rows.iter().enumerate() {
+            let label = metrics.row_label(c, ri);
+            let row_index = ri.to_string();
+            for (t, v) in timestamps.iter().zip(row) {
+                wtr.write_record([
+                    t,
+                    "system",
+                    &category,
+                    "",
+                    &label,
+                    &row_index,
+                    &format!("{:.*}", precision, v),
+                ])
+                .unwrap();
+            }
+        }
+    }
+
+    wtr.flush().unwrap();
+}
+
+// Bytes are whole numbers
+fn output_precision(precision: usize, scale: f32) -> usize {
+    if scale == 1. {
+        precision
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_long() {
+        let path = std::env::temp_dir().join(format!("precord-append-{}.csv", std::process::id()));
+        let process = ProcessInfo {
+            pid: 42,
+            name: "app".to_string(),
+            command: "app --flag".to_string(),
+            exe: None,
+            start_time: None,
+            values: vec![vec![1., 2.]],
+            valid: true,
+            ended: None,
+        };
+        let metrics = SystemMetrics {
+            rows: vec![vec![3., 4.]],
+            labels: vec![],
+        };
+        let timestamps = [chrono::Local::now(), chrono::Local::now()];
+
+        for append in [false, true, true] {
+            consume(
+                &path,
+                &[ProcessCategory::Cpu],
+                &[SystemCategory::Mem],
+                &timestamps,
+                std::slice::from_ref(&process),
+                std::slice::from_ref(&metrics),
+                Options {
+                    append,
+                    layout: CsvLayout::Long,
+                    timestamp: TimestampFormat::Rfc3339,
+                    precision: None,
+                    raw_units: false,
+                },
+            );
+        }
+
+        let mut rdr = csv::Reader::from_path(&path).unwrap();
+        let headers = rdr.headers().unwrap().clone();
+        let rows: Vec<_> = rdr.records().map(|r| r.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(&headers[6], "value");
+        // Every write adds 2 process and 2 system rows under the single title row
+        assert_eq!(rows.len(), 12);
+        for chunk in rows.chunks(4) {
+            let values: Vec<_> = chunk.iter().map(|r| (&r[1], &r[2], &r[6])).collect();
+            assert_eq!(
+                values,
+                [
+                    ("process", "Cpu", "1.00"),
+                    ("process", "Cpu", "2.00"),
+                    ("system", "Mem", "3.00"),
+                    ("system", "Mem", "4.00"),
+                ]
+            );
+        }
+    }
 }
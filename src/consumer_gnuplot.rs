@@ -0,0 +1,134 @@
+use crate::opt::{ProcessCategory, SystemCategory};
+use crate::types::{ProcessInfo, SystemMetrics};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// Without spaces, columns of the data file are separated by them
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+// Writes the samples to `data` and a script plotting every category from it to `path`
+pub fn consume<P: AsRef<Path>, D: AsRef<Path>>(
+    path: P,
+    data: D,
+    proc_categories: &[ProcessCategory],
+    sys_categories: &[SystemCategory],
+    timestamps: &[chrono::DateTime<chrono::Local>],
+    processes: &[ProcessInfo],
+    system_metrics: &[SystemMetrics],
+) {
+    let data = data.as_ref();
+
+    let mut charts = vec![];
+    for (ci, &c) in proc_categories.iter().enumerate() {
+        let series = processes
+            .iter()
+            .map(|p| (format!("{}({})", p.name, p.pid), p.values[ci].as_slice()))
+            .collect();
+        charts.push(Chart {
+            title: format!("Process {:?}", c),
+            unit: c.unit(),
+            series,
+        });
+    }
+    for (i, &c) in sys_categories.iter().enumerate() {
+        let metrics = &system_metrics[i];
+        let series = metrics
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(ri, row)| (metrics.row_label(c, ri), row.as_slice()))
+            .collect();
+        charts.push(Chart {
+            title: format!("System {:?}", c),
+            unit: c.unit(),
+            series,
+        });
+    }
+
+    // Data, a column per series after the timestamp
+    let file = File::create(data).unwrap();
+    let mut wtr = BufWriter::new(&file);
+    write!(wtr, "# timestamp").unwrap();
+    for chart in charts.iter() {
+        for (name, _) in chart.series.iter() {
+            write!(wtr, "\t{}: {}", chart.title, name).unwrap();
+        }
+    }
+    writeln!(wtr).unwrap();
+    for (i, t) in timestamps.iter().enumerate() {
+        write!(wtr, "{}", t.format(TIME_FORMAT)).unwrap();
+        for chart in charts.iter() {
+            for (_, values) in chart.series.iter() {
+                write!(wtr, " {:.2}", values.get(i).copied().unwrap_or(0.)).unwrap();
+            }
+        }
+        writeln!(wtr).unwrap();
+    }
+    wtr.flush().unwrap();
+    drop(wtr);
+    file.sync_all().unwrap();
+
+    // Script, a chart per category stacked by multiplot
+    let data_name = data
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file = File::create(path).unwrap();
+    let mut wtr = BufWriter::new(&file);
+    writeln!(wtr, "set xdata time").unwrap();
+    writeln!(wtr, "set timefmt \"{}\"", TIME_FORMAT).unwrap();
+    writeln!(wtr, "set format x \"%H:%M:%S\"").unwrap();
+    writeln!(wtr, "set key outside right").unwrap();
+    writeln!(wtr, "set grid").unwrap();
+    writeln!(wtr, "set multiplot layout {},1", charts.len().max(1)).unwrap();
+
+    let mut column = 2;
+    for chart in charts.iter() {
+        writeln!(wtr).unwrap();
+        writeln!(wtr, "set title \"{}\" noenhanced", quote(&chart.title)).unwrap();
+        writeln!(wtr, "set ylabel \"{}\" noenhanced", quote(chart.unit)).unwrap();
+        if chart.series.is_empty() {
+            continue;
+        }
+
+        let plots: Vec<_> = chart
+            .series
+            .iter()
+            .enumerate()
+            .map(|(si, (name, _))| {
+                let source = if si == 0 {
+                    format!("\"{}\"", quote(&data_name))
+                } else {
+                    "''".to_string()
+                };
+                format!(
+                    "{} using 1:{} with lines title \"{}\" noenhanced",
+                    source,
+                    column + si,
+                    quote(name)
+                )
+            })
+            .collect();
+        writeln!(wtr, "plot {}", plots.join(", \\\n     ")).unwrap();
+        column += chart.series.len();
+    }
+
+    writeln!(wtr).unwrap();
+    writeln!(wtr, "unset multiplot").unwrap();
+    wtr.flush().unwrap();
+    drop(wtr);
+    file.sync_all().unwrap();
+}
+
+struct Chart<'a> {
+    title: String,
+    unit: &'a str,
+    // Name and values of every series
+    series: Vec<(String, &'a [f32])>,
+}
+
+// Strings of gnuplot are double quoted with backslash escapes
+fn quote(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,5 +1,5 @@
 use crate::opt::{ProcessCategory, SystemCategory};
-use crate::types::{ProcessInfo, SystemMetrics};
+use crate::types::{integral, moving_average, ProcessInfo, Smoothing, SystemMetrics, YAxisMax};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fs::File;
@@ -10,6 +10,24 @@ const CHART_HEIGHT: usize = 800;
 const CHART_PADDING_LEFT: usize = 50;
 const CHART_PADDING_RIGHT: usize = 300;
 const CHART_PADDING_TOP_BOTTOM: usize = 100;
+// Default palette of echarts, a raw line shares the color of its moving average
+const ECHARTS_COLORS: [&str; 9] = [
+    "#5470c6", "#91cc75", "#fac858", "#ee6666", "#73c0de", "#3ba272", "#fc8452", "#9a60b4",
+    "#ea7ccc",
+];
+const ECHARTS_CDN: &str = "https://cdn.jsdelivr.net/npm/echarts@5.2.2/dist/echarts.min.js";
+#[cfg(feature = "embed-assets")]
+const ECHARTS_JS: &str = include_str!("../asset/echarts.min.js");
+
+pub struct Options<'a> {
+    pub embed_assets: bool,
+    pub y_max: &'a YAxisMax,
+    // Time every sample covered, totals of rate categories are left out when empty
+    pub durations: &'a [f32],
+    pub smoothing: Option<Smoothing>,
+    // A single dataZoom for the x-axes of every chart
+    pub link_zoom: bool,
+}
 
 pub fn consume<P: AsRef<Path>>(
     output: P,
@@ -18,6 +36,7 @@ pub fn consume<P: AsRef<Path>>(
     timestamps: &[chrono::DateTime<chrono::Local>],
     processes: &[ProcessInfo],
     system_metrics: &[SystemMetrics],
+    options: Options,
 ) {
     if timestamps.is_empty() {
         return;
@@ -32,38 +51,68 @@ pub fn consume<P: AsRef<Path>>(
     let mut data_zooms = vec![];
     let mut tooltips = vec![];
 
-    for ci in 0..proc_category.len() {
-        let mut max_value: f32 = proc_category[ci].lower_bound();
-        let category_title = format!("Process {:?}", proc_category[ci]);
-        let unit = proc_category[ci].unit();
+    for (ci, &proc_c) in proc_category.iter().enumerate() {
+        let mut max_value: f32 = proc_c.lower_bound();
+        let category_title = format!("Process {:?}", proc_c);
+        let unit = proc_c.unit();
         let mut total = vec![];
         let mut legend_c = vec![];
         let mut tooltip = HashMap::new();
+        let total_label = |total: Option<f32>| match total.filter(|_| proc_c.is_rate()) {
+            Some(total) => format!(" / Total({})", proc_c.format_total(total)),
+            None => String::new(),
+        };
 
         for p in processes {
             let avg = p.avg_value(ci);
+            let smoothed = options
+                .smoothing
+                .map(|smoothing| p.smoothed_values(ci, smoothing.window));
 
             total.extend_from_slice(
                 vec![0.0; p.values[ci].len().saturating_sub(total.len())].as_slice(),
             );
 
-            let data: Vec<_> = p.values[ci]
+            // The line stops where the process exited
+            let data: Vec<_> = p
+                .live_values(ci)
                 .iter()
                 .copied()
                 .enumerate()
-                .zip(timestamps.into_iter())
+                .zip(timestamps)
                 .map(|((i, v), t)| {
                     max_value = max_value.max(v);
                     total[i] += v;
 
-                    json!([t, v])
+                    json!([t, smoothed.as_ref().map_or(v, |s| s[i])])
                 })
                 .collect();
-            let name = format!("{} / AVG({:.2}{}) / {}", p.pid, avg, unit, &p.name);
+            let name = format!(
+                "{} / AVG({:.2}{}) / P95({:.2}{}) / MAX({:.2}{}){} / {}",
+                p.pid,
+                avg,
+                unit,
+                p.percentile(ci, 95.),
+                unit,
+                p.max_value(ci),
+                unit,
+                total_label(p.total_value(ci, options.durations)),
+                &p.name
+            );
+            let color = ECHARTS_COLORS[legend_c.len() % ECHARTS_COLORS.len()];
+            if options.smoothing.is_some_and(|s| s.keeps_raw()) {
+                series.push(faded_series(
+                    &name,
+                    color,
+                    grids.len(),
+                    p.live_values(ci).iter().copied().zip(timestamps),
+                ));
+            }
             series.push(json!({
                 "name": &name,
                 "type": "line",
                 "showSymbol": false,
+                "color": color,
                 "xAxisIndex": grids.len(),
                 "yAxisIndex": grids.len(),
                 "data": data,
@@ -82,20 +131,41 @@ pub fn consume<P: AsRef<Path>>(
 
         if processes.len() > 1 {
             let avg: f32 = total.iter().copied().sum::<f32>() / total.len() as f32;
+            let name = format!(
+                "Total / AVG({:.2}{}){}",
+                avg,
+                unit,
+                total_label(integral(&total, options.durations))
+            );
 
+            let color = ECHARTS_COLORS[legend_c.len() % ECHARTS_COLORS.len()];
+            for &v in total.iter() {
+                max_value = max_value.max(v);
+            }
+            let total = match options.smoothing {
+                Some(smoothing) => {
+                    if smoothing.keeps_raw() {
+                        series.push(faded_series(
+                            &name,
+                            color,
+                            grids.len(),
+                            total.iter().copied().zip(timestamps),
+                        ));
+                    }
+                    moving_average(&total, smoothing.window)
+                }
+                None => total,
+            };
             let data: Vec<_> = total
                 .into_iter()
-                .zip(timestamps.into_iter())
-                .map(|(v, t)| {
-                    max_value = max_value.max(v);
-
-                    json!([t, v])
-                })
+                .zip(timestamps)
+                .map(|(v, t)| json!([t, v]))
                 .collect();
             series.push(json!({
-                "name": format!("Total / AVG({:.2}{})", avg, unit),
+                "name": &name,
                 "type": "line",
                 "showSymbol": false,
+                "color": color,
                 "xAxisIndex": grids.len(),
                 "yAxisIndex": grids.len(),
                 "data": data,
@@ -107,7 +177,7 @@ pub fn consume<P: AsRef<Path>>(
                 },
             }));
             legend_c.push(json!({
-                "name": format!("Total / AVG({:.2}{})", avg, unit),
+                "name": &name,
             }));
         }
 
@@ -118,7 +188,7 @@ pub fn consume<P: AsRef<Path>>(
         y_axis.push(json!({
             "gridIndex": grids.len(),
             "min": 0.0,
-            "max": max_value.ceil(),
+            "max": options.y_max.process(proc_c).unwrap_or(max_value.ceil()),
             "axisLabel": {
                 "formatter": format!("{{value}}{}", unit),
             },
@@ -153,7 +223,7 @@ pub fn consume<P: AsRef<Path>>(
         tooltips.push(tooltip);
     }
 
-    for (i, &sys_c) in sys_category.into_iter().enumerate() {
+    for (i, &sys_c) in sys_category.iter().enumerate() {
         let metrics = &system_metrics[i];
         let max_value = metrics.max().unwrap_or(0.).max(sys_c.lower_bound());
         let category_title = format!("System {:?}", sys_c);
@@ -163,17 +233,41 @@ pub fn consume<P: AsRef<Path>>(
 
         for (si, row) in metrics.rows.iter().enumerate() {
             let avg = metrics.row_avg(si).unwrap_or(0.0);
-            let data: Vec<_> = row
+            let smoothed = options
+                .smoothing
+                .and_then(|smoothing| metrics.row_smoothed(si, smoothing.window));
+            let data: Vec<_> = smoothed
+                .as_ref()
+                .unwrap_or(row)
                 .iter()
                 .copied()
-                .zip(timestamps.into_iter())
+                .zip(timestamps)
                 .map(|(v, t)| json!([t, v]))
                 .collect();
-            let name = format!("{:?}{} / AVG({:.2}{})", sys_c, si, avg, unit);
+            let name = format!(
+                "{} / AVG({:.2}{}) / P95({:.2}{}) / MAX({:.2}{})",
+                metrics.row_label(sys_c, si),
+                avg,
+                unit,
+                metrics.row_percentile(si, 95.).unwrap_or(0.0),
+                unit,
+                metrics.row_percentile(si, 100.).unwrap_or(0.0),
+                unit
+            );
+            let color = ECHARTS_COLORS[legend_c.len() % ECHARTS_COLORS.len()];
+            if options.smoothing.is_some_and(|s| s.keeps_raw()) {
+                series.push(faded_series(
+                    &name,
+                    color,
+                    grids.len(),
+                    row.iter().copied().zip(timestamps),
+                ));
+            }
             series.push(json!({
                 "name": &name,
                 "type": "line",
                 "showSymbol": false,
+                "color": color,
                 "xAxisIndex": grids.len(),
                 "yAxisIndex": grids.len(),
                 "data": data,
@@ -196,7 +290,7 @@ pub fn consume<P: AsRef<Path>>(
         y_axis.push(json!({
             "gridIndex": grids.len(),
             "min": 0.0,
-            "max": max_value.ceil(),
+            "max": options.y_max.system(sys_c).unwrap_or(max_value.ceil()),
             "axisLabel": {
                 "formatter": format!("{{value}}{}", unit),
             },
@@ -232,6 +326,13 @@ pub fn consume<P: AsRef<Path>>(
     }
 
     let grid_len = grids.len();
+    if options.link_zoom {
+        // Placed under the first chart
+        data_zooms.truncate(1);
+        if let Some(data_zoom) = data_zooms.first_mut() {
+            data_zoom["xAxisIndex"] = json!((0..grid_len).collect::<Vec<_>>());
+        }
+    }
     let option = json!({
         "tooltip": {
             "show": true,
@@ -251,7 +352,10 @@ pub fn consume<P: AsRef<Path>>(
 <html>
   <head>
     <meta charset="utf-8" />
-    <script src="https://cdn.jsdelivr.net/npm/echarts@5.2.2/dist/echarts.min.js"></script>
+    "#
+    .to_string()
+        + &echarts_script(options.embed_assets)
+        + r#"
     <style>
         #main {
             margin: 20px auto;
@@ -260,13 +364,11 @@ pub fn consume<P: AsRef<Path>>(
   </head>
   <body>
     <div id="main" style="height: "#
-        .to_string()
         + &(800 * grid_len).to_string()
-        + &r#"px;"></div>
+        + r#"px;"></div>
     <script>
       var myChart = echarts.init(document.getElementById('main'), null, { renderer: 'svg' });
       var option = "#
-            .to_string()
         + &option.to_string()
         + r#";
 
@@ -295,3 +397,37 @@ pub fn consume<P: AsRef<Path>>(
     file.write_all(html_content.as_bytes()).unwrap();
     file.sync_all().unwrap();
 }
+
+fn echarts_script(embed_assets: bool) -> String {
+    #[cfg(feature = "embed-assets")]
+    if embed_assets {
+        return format!("<script>{}</script>", ECHARTS_JS);
+    }
+    let _ = embed_assets;
+    format!(r#"<script src="{}"></script>"#, ECHARTS_CDN)
+}
+
+// The raw line under a moving average, toggled with it by the shared legend name
+fn faded_series<'a>(
+    name: &str,
+    color: &str,
+    grid: usize,
+    values: impl Iterator<Item = (f32, &'a chrono::DateTime<chrono::Local>)>,
+) -> serde_json::Value {
+    let data: Vec<_> = values.map(|(v, t)| json!([t, v])).collect();
+    json!({
+        "name": name,
+        "type": "line",
+        "showSymbol": false,
+        "color": color,
+        "xAxisIndex": grid,
+        "yAxisIndex": grid,
+        "data": data,
+        "lineStyle": {
+            "opacity": 0.3,
+        },
+        "emphasis": {
+            "focus": "series",
+        },
+    })
+}
@@ -0,0 +1,79 @@
+use crate::opt::{ProcessCategory, SystemCategory};
+use crate::types::{ProcessInfo, SystemMetrics};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// InfluxDB line protocol, a line per sample of every process and system row
+pub fn consume<P: AsRef<Path>>(
+    path: P,
+    proc_categories: &[ProcessCategory],
+    sys_categories: &[SystemCategory],
+    timestamps: &[chrono::DateTime<chrono::Local>],
+    processes: &[ProcessInfo],
+    system_metrics: &[SystemMetrics],
+) {
+    let file = File::create(path).unwrap();
+    let mut wtr = BufWriter::new(&file);
+
+    let timestamps: Vec<_> = timestamps
+        .iter()
+        .map(|t| t.timestamp_nanos_opt().unwrap_or_default())
+        .collect();
+    let proc_keys: Vec<_> = proc_categories.iter().map(key).collect();
+
+    // Process
+    if !proc_categories.is_empty() {
+        for p in processes {
+            let tags = format!("pid={},name={}", p.pid, escape(&p.name));
+            for (i, t) in timestamps.iter().enumerate() {
+                let fields: Vec<_> = proc_keys
+                    .iter()
+                    .enumerate()
+                    .map(|(ci, k)| format!("{}={}", k, p.values[ci][i]))
+                    .collect();
+                writeln!(wtr, "precord,{} {} {}", tags, fields.join(","), t).unwrap();
+            }
+        }
+    }
+
+    // System
+    for (i, c) in sys_categories.iter().enumerate() {
+        let metrics = &system_metrics[i];
+        let category = key(c);
+        for (ri, row) in metrics.rows.iter().enumerate() {
+            let mut tags = format!("category={},row={}", category, ri);
+            if let Some(label) = metrics.labels.get(ri) {
+                tags.push_str(&format!(",label={}", escape(label)));
+            }
+            for (t, v) in timestamps.iter().zip(row) {
+                writeln!(wtr, "precord_system,{} value={} {}", tags, v, t).unwrap();
+            }
+        }
+    }
+
+    wtr.flush().unwrap();
+    drop(wtr);
+    file.sync_all().unwrap();
+}
+
+// Name of the category in json outputs, e.g., `net_in`
+fn key<C: Serialize>(c: &C) -> String {
+    serde_json::to_value(c)
+        .ok()
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+// Commas, equal signs and spaces delimit tags
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
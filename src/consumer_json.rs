@@ -1,11 +1,13 @@
-use crate::opt::{ProcessCategory, SystemCategory};
-use crate::types::{ProcessInfo, SystemMetrics};
+use crate::opt::{ProcessCategory, SystemCategory, TimestampFormat};
+use crate::types::{ProcessInfo, SystemMetrics, Timestamp};
 use crate::Pid;
 use serde::Serialize;
-use serde_with::with_prefix;
 use std::collections::HashMap;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+// Version 1 nests records under `process_records`/`sys_records`, replacing the old flat shape
+pub const SCHEMA_VERSION: &str = "1";
 
 pub fn consume<P: AsRef<Path>>(
     path: P,
@@ -14,13 +16,29 @@ pub fn consume<P: AsRef<Path>>(
     timestamps: &[chrono::DateTime<chrono::Local>],
     processes: &[ProcessInfo],
     system_metrics: &[SystemMetrics],
+    mut meta: Meta,
 ) {
+    let timestamp = meta.timestamp;
+    let durations = meta.sample_durations_secs;
+    let raw_units = meta.raw_units;
+    let summary_only = meta.summary_only;
+    if summary_only {
+        meta.sample_durations_secs = &[];
+    }
     let file = File::create(path).unwrap();
 
-    let mut json_output = JsonOutput::default();
+    let mut json_output = JsonOutput {
+        version: SCHEMA_VERSION,
+        meta,
+        process_units: HashMap::new(),
+        sys_units: HashMap::new(),
+        process_records: HashMap::new(),
+        sys_records: HashMap::new(),
+    };
 
     // Process
-    for (ci, &c) in proc_categories.into_iter().enumerate() {
+    for (ci, &c) in proc_categories.iter().enumerate() {
+        let (unit, _) = c.output_unit(raw_units);
         let mut target = vec![];
 
         for p in processes {
@@ -28,38 +46,73 @@ pub fn consume<P: AsRef<Path>>(
                 pid: p.pid,
                 name: p.name.clone(),
                 command: p.command.clone(),
-                records: timestamps
-                    .iter()
-                    .enumerate()
-                    .map(|(i, t)| Record {
-                        timestamp: t.to_rfc3339(),
-                        value: p.values[ci][i],
-                    })
-                    .collect(),
+                exe: p.exe.clone(),
+                start_time: p
+                    .start_time
+                    .and_then(|secs| chrono::DateTime::from_timestamp(secs as _, 0))
+                    .map(|t| timestamp.format(&t.with_timezone(&chrono::Local))),
+                ended_at: p
+                    .ended
+                    .and_then(|i| timestamps.get(i))
+                    .map(|t| timestamp.format(t)),
+                stats: Stats {
+                    avg: p.avg_value(ci),
+                    min: p.min_value(ci),
+                    max: p.max_value(ci),
+                    p50: p.percentile(ci, 50.),
+                    p95: p.percentile(ci, 95.),
+                    p99: p.percentile(ci, 99.),
+                },
+                total: p.total_value(ci, durations).filter(|_| c.is_rate()),
+                records: (!summary_only).then(|| {
+                    timestamps
+                        .iter()
+                        .enumerate()
+                        .map(|(i, t)| Record {
+                            timestamp: timestamp.format(t),
+                            value: p.values[ci][i],
+                        })
+                        .collect()
+                }),
             });
         }
 
+        json_output.process_units.insert(c, unit);
         json_output.process_records.insert(c, target);
     }
 
     // System
-    for (i, &c) in sys_categories.into_iter().enumerate() {
+    for (i, &c) in sys_categories.iter().enumerate() {
+        let (unit, _) = c.output_unit(raw_units);
         let metrics = &system_metrics[i];
         let target: Vec<_> = metrics
             .rows
             .iter()
-            .map(|row| SystemRecord {
-                records: timestamps
-                    .iter()
-                    .enumerate()
-                    .map(|(i, t)| Record {
-                        timestamp: t.to_rfc3339(),
-                        value: row[i],
-                    })
-                    .collect(),
+            .enumerate()
+            .map(|(ri, row)| SystemRecord {
+                label: metrics.labels.get(ri).cloned(),
+                stats: Stats {
+                    avg: metrics.row_avg(ri).unwrap_or(0.),
+                    min: metrics.row_percentile(ri, 0.).unwrap_or(0.),
+                    max: metrics.row_percentile(ri, 100.).unwrap_or(0.),
+                    p50: metrics.row_percentile(ri, 50.).unwrap_or(0.),
+                    p95: metrics.row_percentile(ri, 95.).unwrap_or(0.),
+                    p99: metrics.row_percentile(ri, 99.).unwrap_or(0.),
+                },
+                records: (!summary_only).then(|| {
+                    timestamps
+                        .iter()
+                        .enumerate()
+                        .map(|(i, t)| Record {
+                            timestamp: timestamp.format(t),
+                            value: row[i],
+                        })
+                        .collect()
+                }),
             })
             .collect();
 
+        json_output.sys_units.insert(c, unit);
         json_output.sys_records.insert(c, target);
     }
 
@@ -67,31 +120,79 @@ pub fn consume<P: AsRef<Path>>(
     file.sync_all().unwrap();
 }
 
+// Recording information written alongside the records
+#[derive(Serialize)]
+pub struct Meta<'a> {
+    pub interval_secs: u64,
+    pub hostname: Option<&'a str>,
+    // Time every sample covered, aligned with the records
+    #[serde(skip_serializing_if = "<[f32]>::is_empty")]
+    pub sample_durations_secs: &'a [f32],
+    // Format of the timestamps of records
+    #[serde(skip)]
+    pub timestamp: TimestampFormat,
+    // Values are sampled as bytes instead of M and KBps, see `process_units`/`sys_units`
+    #[serde(skip)]
+    pub raw_units: bool,
+    // Records are left out, only stats are written
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub summary_only: bool,
+}
+
 #[derive(Serialize)]
 struct Record {
-    timestamp: String,
+    timestamp: Timestamp,
     value: f32,
 }
 
+#[derive(Serialize)]
+struct Stats {
+    avg: f32,
+    min: f32,
+    max: f32,
+    p50: f32,
+    p95: f32,
+    p99: f32,
+}
+
 #[derive(Serialize)]
 struct ProcessRecord {
     pid: Pid,
     name: String,
     command: String,
-    records: Vec<Record>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exe: Option<PathBuf>,
+    start_time: Option<Timestamp>,
+    // Timestamp of the first sample after the process exited, values from it are zeros
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ended_at: Option<Timestamp>,
+    #[serde(flatten)]
+    stats: Stats,
+    // Integral of per-second categories, KB for KBps (B for Bps) and a count for /s
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    records: Option<Vec<Record>>,
 }
 
 #[derive(Serialize)]
 struct SystemRecord {
-    records: Vec<Record>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(flatten)]
+    stats: Stats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    records: Option<Vec<Record>>,
 }
 
-with_prefix!(prefix_sys "sys_");
-
-#[derive(Default, Serialize)]
-struct JsonOutput {
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    version: &'static str,
     #[serde(flatten)]
+    meta: Meta<'a>,
+    // Unit of the values of every category
+    process_units: HashMap<ProcessCategory, &'static str>,
+    sys_units: HashMap<SystemCategory, &'static str>,
     process_records: HashMap<ProcessCategory, Vec<ProcessRecord>>,
-    #[serde(flatten, with = "prefix_sys")]
     sys_records: HashMap<SystemCategory, Vec<SystemRecord>>,
 }
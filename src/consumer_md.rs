@@ -0,0 +1,97 @@
+use crate::opt::{ProcessCategory, SystemCategory};
+use crate::types::{ProcessInfo, SystemMetrics};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const STATS: [&str; 4] = ["AVG", "MIN", "MAX", "P95"];
+
+pub fn consume<P: AsRef<Path>>(
+    path: P,
+    proc_categories: &[ProcessCategory],
+    sys_categories: &[SystemCategory],
+    timestamps: &[chrono::DateTime<chrono::Local>],
+    processes: &[ProcessInfo],
+    system_metrics: &[SystemMetrics],
+) {
+    let file = File::create(path).unwrap();
+    let mut wtr = BufWriter::new(&file);
+
+    if let (Some(first), Some(last)) = (timestamps.first(), timestamps.last()) {
+        writeln!(wtr, "{} - {}", first.to_rfc3339(), last.to_rfc3339()).unwrap();
+        writeln!(wtr).unwrap();
+    }
+
+    // Process, a column per process
+    for (ci, &c) in proc_categories.iter().enumerate() {
+        let unit = c.unit();
+
+        writeln!(wtr, "### Process {:?}", c).unwrap();
+        writeln!(wtr).unwrap();
+        let header: Vec<_> = processes
+            .iter()
+            .map(|p| escape(&format!("{}({})", p.name, p.pid)))
+            .collect();
+        write_row(&mut wtr, "", header.iter().map(String::as_str));
+        write_row(&mut wtr, "---", processes.iter().map(|_| "---:"));
+        for stat in STATS {
+            let values: Vec<_> = processes
+                .iter()
+                .map(|p| {
+                    let v = match stat {
+                        "AVG" => p.avg_value(ci),
+                        "MIN" => p.min_value(ci),
+                        "MAX" => p.max_value(ci),
+                        _ => p.percentile(ci, 95.),
+                    };
+                    format!("{:.2}{}", v, unit)
+                })
+                .collect();
+            write_row(&mut wtr, stat, values.iter().map(String::as_str));
+        }
+        writeln!(wtr).unwrap();
+    }
+
+    // System, a row per metrics row
+    for (i, &c) in sys_categories.iter().enumerate() {
+        let metrics = &system_metrics[i];
+        let unit = c.unit();
+
+        writeln!(wtr, "### System {:?}", c).unwrap();
+        writeln!(wtr).unwrap();
+        write_row(&mut wtr, "", STATS);
+        write_row(&mut wtr, "---", STATS.map(|_| "---:"));
+        for ri in 0..metrics.rows.len() {
+            let values = [
+                metrics.row_avg(ri),
+                metrics.row_percentile(ri, 0.),
+                metrics.row_percentile(ri, 100.),
+                metrics.row_percentile(ri, 95.),
+            ]
+            .map(|v| format!("{:.2}{}", v.unwrap_or(0.), unit));
+            write_row(
+                &mut wtr,
+                &escape(&metrics.row_label(c, ri)),
+                values.iter().map(String::as_str),
+            );
+        }
+        writeln!(wtr).unwrap();
+    }
+
+    wtr.flush().unwrap();
+    drop(wtr);
+    file.sync_all().unwrap();
+}
+
+fn write_row<'a, W: Write, I: IntoIterator<Item = &'a str>>(wtr: &mut W, first: &str, cells: I) {
+    write!(wtr, "| {} |", first).unwrap();
+    for cell in cells {
+        write!(wtr, " {} |", cell).unwrap();
+    }
+    writeln!(wtr).unwrap();
+}
+
+// `|` would end a cell
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|")
+}
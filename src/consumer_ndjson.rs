@@ -0,0 +1,82 @@
+use crate::opt::{ProcessCategory, SystemCategory};
+use crate::types::{ProcessInfo, SystemMetrics, Timestamp};
+use crate::Pid;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+// Write the latest sample as one line
+pub fn consume<W: Write>(
+    mut wtr: W,
+    proc_categories: &[ProcessCategory],
+    sys_categories: &[SystemCategory],
+    processes: &[ProcessInfo],
+    system_metrics: &[SystemMetrics],
+    meta: Meta,
+) {
+    let raw_units = meta.raw_units;
+    let mut line = JsonLine {
+        timestamp: meta.timestamp,
+        duration_secs: meta.duration.as_secs_f32(),
+        process: HashMap::new(),
+        system: HashMap::new(),
+    };
+
+    // Process
+    for (ci, &c) in proc_categories.iter().enumerate() {
+        let (_, scale) = c.output_unit(raw_units);
+        let target = processes
+            .iter()
+            .filter_map(|p| {
+                Some(ProcessValue {
+                    pid: p.pid,
+                    name: &p.name,
+                    value: *p.values[ci].last()? / scale,
+                })
+            })
+            .collect();
+
+        line.process.insert(c, target);
+    }
+
+    // System
+    for (i, &c) in sys_categories.iter().enumerate() {
+        let (_, scale) = c.output_unit(raw_units);
+        let target = system_metrics[i]
+            .rows
+            .iter()
+            .filter_map(|row| Some(row.last()? / scale))
+            .collect();
+
+        line.system.insert(c, target);
+    }
+
+    serde_json::to_writer(&mut wtr, &line).unwrap();
+    wtr.write_all(b"\n").unwrap();
+    wtr.flush().unwrap();
+}
+
+pub struct Meta {
+    pub timestamp: Timestamp,
+    // Time the sample covered
+    pub duration: Duration,
+    // Values are sampled as bytes, they're turned back into M and KBps
+    pub raw_units: bool,
+}
+
+#[derive(Serialize)]
+struct ProcessValue<'a> {
+    pid: Pid,
+    name: &'a str,
+    value: f32,
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: Timestamp,
+    // Time the sample covered, longer than the interval when sampling stalls
+    duration_secs: f32,
+    process: HashMap<ProcessCategory, Vec<ProcessValue<'a>>>,
+    system: HashMap<SystemCategory, Vec<f32>>,
+}
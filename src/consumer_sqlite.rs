@@ -0,0 +1,59 @@
+use crate::opt::{ProcessCategory, SystemCategory};
+use crate::types::{ProcessInfo, SystemMetrics};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+pub fn consume<P: AsRef<Path>>(
+    path: P,
+    proc_categories: &[ProcessCategory],
+    sys_categories: &[SystemCategory],
+    timestamps: &[chrono::DateTime<chrono::Local>],
+    processes: &[ProcessInfo],
+    system_metrics: &[SystemMetrics],
+) {
+    let mut conn = Connection::open(path).unwrap();
+    let tx = conn.transaction().unwrap();
+
+    // Tables are recreated so repeated saves don't duplicate rows
+    tx.execute_batch(
+        "DROP TABLE IF EXISTS samples;
+        DROP TABLE IF EXISTS system_samples;
+        CREATE TABLE samples (timestamp TEXT NOT NULL, pid INTEGER NOT NULL, category TEXT NOT NULL, value REAL NOT NULL);
+        CREATE TABLE system_samples (timestamp TEXT NOT NULL, category TEXT NOT NULL, row_index INTEGER NOT NULL, value REAL NOT NULL);",
+    )
+    .unwrap();
+
+    {
+        let timestamps: Vec<_> = timestamps.iter().map(|t| t.to_rfc3339()).collect();
+
+        // Process
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO samples (timestamp, pid, category, value) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .unwrap();
+        for (ci, &c) in proc_categories.iter().enumerate() {
+            let category = format!("{:?}", c);
+            for p in processes {
+                for (t, v) in timestamps.iter().zip(&p.values[ci]) {
+                    stmt.execute(params![t, p.pid, category, v]).unwrap();
+                }
+            }
+        }
+
+        // System
+        let mut stmt = tx
+            .prepare("INSERT INTO system_samples (timestamp, category, row_index, value) VALUES (?1, ?2, ?3, ?4)")
+            .unwrap();
+        for (i, &c) in sys_categories.iter().enumerate() {
+            let category = format!("{:?}", c);
+            for (ri, row) in system_metrics[i].rows.iter().enumerate() {
+                for (t, v) in timestamps.iter().zip(row) {
+                    stmt.execute(params![t, category, ri, v]).unwrap();
+                }
+            }
+        }
+    }
+
+    tx.commit().unwrap();
+}
@@ -1,8 +1,28 @@
 use crate::opt::{ProcessCategory, SystemCategory};
-use crate::types::{ProcessInfo, SystemMetrics};
+use crate::types::{
+    ellipsize_middle, integral, moving_average, ProcessInfo, Smoothing, SystemMetrics, YAxisMax,
+};
 use plotters::prelude::*;
 use std::path::Path;
 
+// Legend entries wrap into columns beyond this count
+const LEGEND_MAX_ROWS: usize = 20;
+const LEGEND_COLUMN_WIDTH: usize = 420;
+// Series drawn on their own in a process chart, the rest are summed into one
+const MAX_SERIES: usize = 20;
+
+pub struct Layout<'a> {
+    pub width: u32,
+    // Height of every category chart
+    pub height: u32,
+    pub y_max: &'a YAxisMax,
+    // Time every sample covered, totals of rate categories are left out when empty
+    pub durations: &'a [f32],
+    // Characters of commands in labels
+    pub command_width: Option<usize>,
+    pub smoothing: Option<Smoothing>,
+}
+
 pub fn consume<P: AsRef<Path>>(
     output: P,
     proc_category: &[ProcessCategory],
@@ -10,15 +30,31 @@ pub fn consume<P: AsRef<Path>>(
     timestamps: &[chrono::DateTime<chrono::Local>],
     processes: &[ProcessInfo],
     system_metrics: &[SystemMetrics],
+    layout: Layout,
 ) {
     if timestamps.is_empty() {
         return;
     }
 
-    let timestamp_range = || timestamps[0].clone()..timestamps.last().cloned().unwrap();
+    // Samples taken in the same instant still span the x-axis
+    let end = timestamps
+        .last()
+        .cloned()
+        .unwrap()
+        .max(timestamps[0] + chrono::Duration::milliseconds(1));
+    let timestamp_range = || timestamps[0]..end;
+
+    let legend_columns = if processes.len() > LEGEND_MAX_ROWS {
+        (layout.width as usize / LEGEND_COLUMN_WIDTH)
+            .max(1)
+            .min(processes.len().div_ceil(LEGEND_MAX_ROWS))
+    } else {
+        1
+    };
+    let legend_rows = processes.len().div_ceil(legend_columns);
 
     let top_height = if !processes.is_empty() {
-        (processes.len() + 2) * 15
+        (legend_rows + 2) * 15
     } else {
         0
     };
@@ -26,8 +62,8 @@ pub fn consume<P: AsRef<Path>>(
     let root = SVGBackend::new(
         &output,
         (
-            1280,
-            (top_height + 720 * (proc_category.len() + sys_category.len())) as u32,
+            layout.width,
+            top_height as u32 + layout.height * (proc_category.len() + sys_category.len()) as u32,
         ),
     )
     .into_drawing_area();
@@ -37,13 +73,22 @@ pub fn consume<P: AsRef<Path>>(
     let default_font = ("sans-serif", 12).into_font();
     let default_style: TextStyle = default_font.into();
 
-    for (i, p) in processes.into_iter().enumerate() {
+    for (i, p) in processes.iter().enumerate() {
         let color = Palette99::pick(i).stroke_width(2).filled();
-        let i = i as i32;
-        let legend = PathElement::new(vec![(60, 23 + i * 15), (80, 23 + i * 15)], color);
+        let x = 60 + (i / legend_rows * LEGEND_COLUMN_WIDTH) as i32;
+        let y = (i % legend_rows) as i32 * 15;
+        let legend = PathElement::new(vec![(x, 23 + y), (x + 20, 23 + y)], color);
         top.draw(&legend).unwrap();
-        let label = format!("{}({}) - {}", p.name, p.pid, p.command);
-        top.draw_text(&label, &default_style, (90, 19 + i * 15))
+        let command = match layout.command_width {
+            Some(width) => ellipsize_middle(&p.command, width),
+            None => p.command.clone(),
+        };
+        let mut label = format!("{}({}) - {}", p.name, p.pid, command);
+        if legend_columns > 1 {
+            // Keep labels inside their column
+            label = label.chars().take(LEGEND_COLUMN_WIDTH / 7 - 6).collect();
+        }
+        top.draw_text(&label, &default_style, (x + 30, 19 + y))
             .unwrap();
     }
 
@@ -69,9 +114,12 @@ pub fn consume<P: AsRef<Path>>(
             max = max.max(t);
         }
 
-        max = max.max(proc_category[idx_c].lower_bound());
+        max = layout
+            .y_max
+            .process(proc_category[idx_c])
+            .unwrap_or_else(|| max.max(proc_category[idx_c].lower_bound()));
 
-        let mut chart = ChartBuilder::on(&area)
+        let mut chart = ChartBuilder::on(area)
             .caption(
                 format!("Process {:?}", proc_category[idx_c]),
                 ("sans-serif", 30).into_font(),
@@ -88,62 +136,157 @@ pub fn consume<P: AsRef<Path>>(
             .draw()
             .unwrap();
 
+        let c = proc_category[idx_c];
+        let total_label = |total: Option<f32>| match total.filter(|_| c.is_rate()) {
+            Some(total) => format!(" / Total({})", c.format_total(total)),
+            None => String::new(),
+        };
+
+        let drawn = drawn_processes(processes, idx_c);
         for (idx, process) in processes.iter().enumerate() {
+            if !drawn[idx] {
+                continue;
+            }
             let color = Palette99::pick(idx).stroke_width(2).filled();
+            // The line stops where the process exited
+            let values = match layout.smoothing {
+                Some(smoothing) => {
+                    if smoothing.keeps_raw() {
+                        chart
+                            .draw_series(LineSeries::new(
+                                timestamps
+                                    .iter()
+                                    .cloned()
+                                    .zip(process.live_values(idx_c).iter().cloned()),
+                                Palette99::pick(idx).mix(0.3),
+                            ))
+                            .unwrap();
+                    }
+                    process.smoothed_values(idx_c, smoothing.window)
+                }
+                None => process.live_values(idx_c).to_vec(),
+            };
             chart
                 .draw_series(LineSeries::new(
-                    timestamps
-                        .into_iter()
-                        .cloned()
-                        .zip(process.values[idx_c].iter().cloned()),
-                    color.clone(),
+                    timestamps.iter().cloned().zip(values),
+                    color,
                 ))
                 .unwrap()
                 .label(format!(
-                    "{}({}) / AVG({:.2}{})",
+                    "{}({}) / AVG({:.2}{}) / P95({:.2}{}) / MAX({:.2}{}){}",
                     &process.name,
                     process.pid,
                     process.avg_value(idx_c),
                     proc_category[idx_c].unit(),
+                    process.percentile(idx_c, 95.),
+                    proc_category[idx_c].unit(),
+                    process.max_value(idx_c),
+                    proc_category[idx_c].unit(),
+                    total_label(process.total_value(idx_c, layout.durations)),
                 ))
-                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.clone()));
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+
+        let others: Vec<_> = processes
+            .iter()
+            .zip(&drawn)
+            .filter(|(_, &drawn)| !drawn)
+            .map(|(p, _)| p)
+            .collect();
+        if !others.is_empty() {
+            let mut values = vec![0.; timestamps.len()];
+            for p in &others {
+                for (a, b) in values.iter_mut().zip(&p.values[idx_c]) {
+                    *a += *b;
+                }
+            }
+            let avg = values.iter().sum::<f32>() / values.len() as f32;
+            let others_total = total_label(integral(&values, layout.durations));
+            let color = BLACK.mix(0.6).stroke_width(2);
+            let values = match layout.smoothing {
+                Some(smoothing) => {
+                    if smoothing.keeps_raw() {
+                        chart
+                            .draw_series(LineSeries::new(
+                                timestamps.iter().cloned().zip(values.iter().copied()),
+                                BLACK.mix(0.2),
+                            ))
+                            .unwrap();
+                    }
+                    moving_average(&values, smoothing.window)
+                }
+                None => values,
+            };
+            chart
+                .draw_series(LineSeries::new(
+                    timestamps.iter().cloned().zip(values),
+                    color,
+                ))
+                .unwrap()
+                .label(format!(
+                    "Others({}) / AVG({:.2}{}){}",
+                    others.len(),
+                    avg,
+                    proc_category[idx_c].unit(),
+                    others_total
+                ))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
         }
 
         if processes.len() > 1 {
             // Total
-            let avg: f32 = total.iter().map(|a| *a).sum::<f32>() / total.len() as f32;
+            let avg: f32 = total.iter().copied().sum::<f32>() / total.len() as f32;
+            let total_total = total_label(integral(&total, layout.durations));
             let color = Palette99::pick(processes.len()).stroke_width(2).filled();
+            let total = match layout.smoothing {
+                Some(smoothing) => {
+                    if smoothing.keeps_raw() {
+                        chart
+                            .draw_series(LineSeries::new(
+                                timestamps.iter().cloned().zip(total.iter().copied()),
+                                Palette99::pick(processes.len()).mix(0.3),
+                            ))
+                            .unwrap();
+                    }
+                    moving_average(&total, smoothing.window)
+                }
+                None => total,
+            };
             chart
                 .draw_series(LineSeries::new(
-                    timestamps.into_iter().cloned().zip(total.into_iter()),
-                    color.clone(),
+                    timestamps.iter().cloned().zip(total),
+                    color,
                 ))
                 .unwrap()
                 .label(format!(
-                    "Total / AVG({:.2}{})",
+                    "Total / AVG({:.2}{}){}",
                     avg,
-                    proc_category[idx_c].unit()
+                    proc_category[idx_c].unit(),
+                    total_total
                 ))
-                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.clone()));
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
         }
 
         chart
             .configure_series_labels()
-            .background_style(&WHITE.mix(0.8))
-            .border_style(&BLACK)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
             .draw()
             .unwrap();
     }
 
     // Draw system
-    for (i, &c) in sys_category.into_iter().enumerate() {
+    for (i, &c) in sys_category.iter().enumerate() {
         let area = &areas[proc_category.len() + i];
         let mut chart;
 
         let metrics = &system_metrics[i];
-        let max = metrics.max().unwrap_or(0.).max(c.lower_bound());
+        let max = layout
+            .y_max
+            .system(c)
+            .unwrap_or_else(|| metrics.max().unwrap_or(0.).max(c.lower_bound()));
 
-        chart = ChartBuilder::on(&area)
+        chart = ChartBuilder::on(area)
             .caption(format!("System {:?}", c), ("sans-serif", 30).into_font())
             .margin(10)
             .x_label_area_size(40)
@@ -159,27 +302,60 @@ pub fn consume<P: AsRef<Path>>(
 
         for (idx, row) in metrics.rows.iter().enumerate() {
             let color = Palette99::pick(idx).stroke_width(2).filled();
+            let row = match layout.smoothing {
+                Some(smoothing) => {
+                    if smoothing.keeps_raw() {
+                        chart
+                            .draw_series(LineSeries::new(
+                                timestamps.iter().cloned().zip(row.iter().copied()),
+                                Palette99::pick(idx).mix(0.3),
+                            ))
+                            .unwrap();
+                    }
+                    metrics
+                        .row_smoothed(idx, smoothing.window)
+                        .unwrap_or_default()
+                }
+                None => row.clone(),
+            };
             chart
-                .draw_series(LineSeries::new(
-                    timestamps.into_iter().cloned().zip(row.iter().copied()),
-                    color.clone(),
-                ))
+                .draw_series(LineSeries::new(timestamps.iter().cloned().zip(row), color))
                 .unwrap()
                 .label(format!(
-                    "{:?}{} / AVG({:.2}{})",
-                    c,
-                    idx,
+                    "{} / AVG({:.2}{}) / P95({:.2}{}) / MAX({:.2}{})",
+                    metrics.row_label(c, idx),
                     metrics.row_avg(idx).unwrap_or(0.),
+                    c.unit(),
+                    metrics.row_percentile(idx, 95.).unwrap_or(0.),
+                    c.unit(),
+                    metrics.row_percentile(idx, 100.).unwrap_or(0.),
                     c.unit()
                 ))
-                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.clone()));
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
         }
 
         chart
             .configure_series_labels()
-            .background_style(&WHITE.mix(0.8))
-            .border_style(&BLACK)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
             .draw()
             .unwrap();
     }
 }
+
+// Processes with the highest averages of the category when there are too many to tell apart
+fn drawn_processes(processes: &[ProcessInfo], idx_c: usize) -> Vec<bool> {
+    let mut drawn = vec![true; processes.len()];
+    if processes.len() > MAX_SERIES {
+        let mut order: Vec<_> = (0..processes.len()).collect();
+        order.sort_by(|&a, &b| {
+            processes[b]
+                .avg_value(idx_c)
+                .total_cmp(&processes[a].avg_value(idx_c))
+        });
+        for &i in &order[MAX_SERIES..] {
+            drawn[i] = false;
+        }
+    }
+    drawn
+}
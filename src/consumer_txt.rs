@@ -0,0 +1,98 @@
+use crate::opt::{ProcessCategory, SystemCategory};
+use crate::types::{ProcessInfo, SystemMetrics};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SPARKLINE_WIDTH: usize = 100;
+
+pub fn consume<P: AsRef<Path>>(
+    path: P,
+    proc_categories: &[ProcessCategory],
+    sys_categories: &[SystemCategory],
+    timestamps: &[chrono::DateTime<chrono::Local>],
+    processes: &[ProcessInfo],
+    system_metrics: &[SystemMetrics],
+) {
+    let file = File::create(path).unwrap();
+    let mut wtr = BufWriter::new(&file);
+
+    if let (Some(first), Some(last)) = (timestamps.first(), timestamps.last()) {
+        writeln!(wtr, "{} - {}", first.to_rfc3339(), last.to_rfc3339()).unwrap();
+        writeln!(wtr).unwrap();
+    }
+
+    // Process
+    for (ci, &c) in proc_categories.iter().enumerate() {
+        let unit = c.unit();
+        let max_value = processes
+            .iter()
+            .flat_map(|p| p.values[ci].iter().copied())
+            .fold(c.lower_bound(), f32::max);
+
+        writeln!(wtr, "Process {:?}", c).unwrap();
+        for p in processes {
+            let max = p.values[ci].iter().copied().fold(0., f32::max);
+            writeln!(
+                wtr,
+                "  {} / {}\n  {} AVG({:.2}{}) / MAX({:.2}{})",
+                p.pid,
+                &p.name,
+                sparkline(&p.values[ci], max_value),
+                p.avg_value(ci),
+                unit,
+                max,
+                unit,
+            )
+            .unwrap();
+        }
+        writeln!(wtr).unwrap();
+    }
+
+    // System
+    for (i, &c) in sys_categories.iter().enumerate() {
+        let metrics = &system_metrics[i];
+        let unit = c.unit();
+        let max_value = metrics.max().unwrap_or(0.).max(c.lower_bound());
+
+        writeln!(wtr, "System {:?}", c).unwrap();
+        for (ri, row) in metrics.rows.iter().enumerate() {
+            let max = row.iter().copied().fold(0., f32::max);
+            writeln!(
+                wtr,
+                "  {}\n  {} AVG({:.2}{}) / MAX({:.2}{})",
+                metrics.row_label(c, ri),
+                sparkline(row, max_value),
+                metrics.row_avg(ri).unwrap_or(0.),
+                unit,
+                max,
+                unit,
+            )
+            .unwrap();
+        }
+        writeln!(wtr).unwrap();
+    }
+
+    wtr.flush().unwrap();
+    drop(wtr);
+    file.sync_all().unwrap();
+}
+
+// Long series are averaged into `SPARKLINE_WIDTH` buckets
+fn sparkline(values: &[f32], max_value: f32) -> String {
+    let bucket = values.len().div_ceil(SPARKLINE_WIDTH).max(1);
+
+    values
+        .chunks(bucket)
+        .map(|chunk| {
+            let v = chunk.iter().sum::<f32>() / chunk.len() as f32;
+            let level = if max_value > 0. {
+                (v / max_value * (BLOCKS.len() - 1) as f32).round() as usize
+            } else {
+                0
+            };
+            BLOCKS[level.min(BLOCKS.len() - 1)]
+        })
+        .collect()
+}
@@ -1,46 +1,168 @@
-use crate::opt::{Opts, ProcessCategory, SystemCategory};
-use crate::types::{ProcessInfo, SystemMetrics};
-use clap::Parser;
-use crossterm::style::Stylize;
+use crate::baseline::Baseline;
+use crate::opt::{
+    Action, BaselineAction, ColorChoice, ConfigFile, CsvLayout, Opts, ProcessCategory, Progress,
+    SystemCategory,
+};
+use crate::types::{ProcessInfo, Smoothing, SystemMetrics};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, FromArgMatches, ValueEnum};
+use crossterm::style::{Color, Stylize};
 use precord_core::{Error, Features, Pid, System};
 use regex::Regex;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use std::{fs, thread};
+use std::{fs, io, mem, thread};
 
+mod baseline;
 mod consumer_csv;
+mod consumer_gnuplot;
 mod consumer_html;
+mod consumer_influx;
 mod consumer_json;
+mod consumer_md;
+mod consumer_ndjson;
+mod consumer_sqlite;
 mod consumer_svg;
+mod consumer_txt;
+mod merge;
 mod opt;
 mod types;
 mod utils;
 
-fn main() {
+// Exit codes for scripting
+const EXIT_NO_SAMPLES: u8 = 2;
+const EXIT_WRITE_FAILED: u8 = 3;
+
+fn main() -> ExitCode {
     let path_re = Regex::new(r"^\{([\w,]+)}$").unwrap();
-    let mut opts: Opts = Opts::parse();
+    let matches = Opts::command().get_matches();
+    let mut opts = Opts::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    if let Some(path) = opts.config.take() {
+        let r = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|s| toml::from_str::<ConfigFile>(&s).map_err(|err| err.to_string()))
+            .and_then(|config| config.merge(&mut opts, &matches));
+        if let Err(err) = r {
+            Opts::command()
+                .error(
+                    ErrorKind::InvalidValue,
+                    format!("invalid config {}: {}", path.display(), err),
+                )
+                .exit();
+        }
+    }
+
+    utils::init_logger(opts.verbose);
+
+    if let Some(Action::Merge { input, output }) = &opts.action {
+        return match merge::merge(input, output, &opts) {
+            Ok(_) => ExitCode::SUCCESS,
+            Err(err) => {
+                log::error!("{}", err);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    // `baseline capture` is a normal recording into a single json
+    if let Some(Action::Baseline {
+        action: BaselineAction::Capture { output },
+    }) = &opts.action
+    {
+        if output.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            log::error!("{}: baselines are captured into .json", output.display());
+            return ExitCode::FAILURE;
+        }
+        opts.output = vec![output.clone()];
+        opts.action = None;
+    }
+
+    let baseline = match opts
+        .baseline
+        .as_deref()
+        .map(|path| Baseline::read(path, opts.raw_units))
+        .transpose()
+    {
+        Ok(baseline) => baseline,
+        Err(err) => {
+            log::error!("{}: {}", opts.baseline.unwrap().display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
 
     if let Some(action) = opts.action {
         for i in 0..2 {
             match action.exec() {
                 Ok(_) => break,
-                Err(Error::AccessDenied) => {
-                    if i == 0 {
-                        utils::adjust_privileges();
-                    } else {
-                        panic!("{:?}", Error::AccessDenied);
-                    }
+                Err(Error::AccessDenied) if i == 0 => utils::adjust_privileges(),
+                Err(err) => {
+                    log::error!("{}", err);
+                    return ExitCode::FAILURE;
                 }
-                Err(err) => panic!("{:?}", err),
             }
         }
-        return;
+        return ExitCode::SUCCESS;
+    }
+
+    let to_stdout = opts
+        .output
+        .iter()
+        .any(|p| p.as_os_str() == utils::STDOUT_PATH);
+    if to_stdout {
+        // The prompt echoes to stdout
+        opts.interactive = false;
     }
 
-    if opts.count.is_none() && opts.time.is_none() {
+    // Samples are the result of `--once`, printed unless stdout carries json lines
+    let print_samples = opts.once && !to_stdout;
+
+    // Colors go where the samples are reported, stdout for `--once` and the log on stderr otherwise
+    let colored = match opts.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                && if print_samples {
+                    io::stdout().is_terminal()
+                } else {
+                    io::stderr().is_terminal()
+                }
+        }
+    };
+    // crossterm itself drops colors under `NO_COLOR`, which `always` overrides
+    crossterm::style::force_color_output(colored);
+    let paint = |text: String, color: Color| {
+        if colored {
+            text.with(color).to_string()
+        } else {
+            text
+        }
+    };
+
+    let report = |message: &str| {
+        if print_samples {
+            println!("{}", message);
+        } else if opts.progress == Progress::Full {
+            log::info!("{}", message);
+        }
+    };
+
+    // Raw mode needs a terminal, e.g., not under nohup or CI
+    if opts.no_interactive || !io::stdin().is_terminal() {
+        opts.interactive = false;
+    }
+
+    if opts.once {
+        opts.count = Some(1);
+        opts.interactive = false;
+    } else if opts.count.is_none() && opts.time.is_none() {
         opts.count = Some(30);
     }
 
@@ -52,12 +174,19 @@ fn main() {
     let sys_category: Vec<_> = opts.category.iter().flat_map(|&c| c.to_system()).collect();
 
     let mut timestamps = vec![];
+    // Seconds each sample actually covered, may exceed the interval when sampling stalls
+    let mut durations = vec![];
 
     let mut features = Features::PROCESS;
 
+    if proc_category.contains(&ProcessCategory::Gpu) {
+        features.insert(Features::PROCESS_GPU);
+    }
     if proc_category.contains(&ProcessCategory::Gpu)
         || proc_category.contains(&ProcessCategory::Vram)
+        || proc_category.contains(&ProcessCategory::VramShared)
         || sys_category.contains(&SystemCategory::Gpu)
+        || sys_category.contains(&SystemCategory::GpuDevices)
     {
         features.insert(Features::GPU);
     }
@@ -69,9 +198,14 @@ fn main() {
     {
         features.insert(Features::NET_TRAFFIC);
     }
-    if proc_category.contains(&ProcessCategory::Kobject) {
+    if proc_category.contains(&ProcessCategory::Kobject)
+        || proc_category.contains(&ProcessCategory::MachPorts)
+    {
         features.insert(Features::K_OBJECT);
     }
+    if proc_category.contains(&ProcessCategory::Power) {
+        features.insert(Features::ENERGY);
+    }
     if sys_category.contains(&SystemCategory::CpuFreq)
         || sys_category.contains(&SystemCategory::NpuPower)
     {
@@ -79,58 +213,209 @@ fn main() {
     }
     if sys_category.contains(&SystemCategory::CpuTemp)
         || sys_category.contains(&SystemCategory::Power)
+        || sys_category.contains(&SystemCategory::BatteryPct)
     {
         features.insert(Features::SMC);
     }
+    if sys_category.contains(&SystemCategory::NetIn)
+        || sys_category.contains(&SystemCategory::NetOut)
+    {
+        features.insert(Features::NETWORK);
+    }
+    if sys_category.contains(&SystemCategory::DiskRead)
+        || sys_category.contains(&SystemCategory::DiskWrite)
+    {
+        features.insert(Features::DISK);
+    }
 
-    let system = System::new(Features::PROCESS, []).unwrap();
+    let mut system = match System::new(Features::PROCESS, []) {
+        Ok(system) => system,
+        Err(err) => {
+            log::error!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if opts.top.is_some() {
+        // CPU usage needs two refreshes to be computed
+        thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        let _ = system.update(Instant::now());
+    }
+
+    // Pids listed by `--pid-file`, `--cgroup`, `--job` or `--app`, synced with them on every sample
+    let cli_pids = opts.process.clone();
+    let mut listed_pids = HashSet::new();
+    let mut paused = HashSet::new();
+    if opts.lists_pids() {
+        listed_pids = opts.listed_pids().unwrap_or_else(|| {
+            log::warn!("Can't read the listed pids");
+            HashSet::new()
+        });
+        for &pid in listed_pids.iter() {
+            if !opts.process.contains(&pid) {
+                opts.process.push(pid);
+            }
+        }
+    }
 
     let mut processes = opts.find_processes(&system, proc_category.len());
 
-    if (processes.is_empty() || proc_category.is_empty()) && sys_category.is_empty() {
-        println!("No tasks available");
-        return;
+    // Dry run of the selection
+    if opts.list {
+        for p in processes.iter() {
+            println!("{} {} {}", p.pid, p.name, p.command);
+        }
+        if processes.is_empty() {
+            log::error!("No processes selected");
+            return ExitCode::from(EXIT_NO_SAMPLES);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if ((processes.is_empty() && !opts.follow_name && !opts.lists_pids())
+        || proc_category.is_empty())
+        && sys_category.is_empty()
+    {
+        log::error!("No tasks available");
+        return ExitCode::from(EXIT_NO_SAMPLES);
     }
 
     let mut system = None;
     for i in 0..2 {
-        match System::new(features, processes.iter().map(|p| p.pid)) {
+        match System::with_interval(
+            features,
+            processes.iter().map(|p| p.pid),
+            Duration::from_secs(opts.interval),
+        ) {
             Ok(system1) => {
                 system = Some(system1);
                 break;
             }
-            Err(Error::AccessDenied) => {
-                if i == 0 {
-                    utils::adjust_privileges();
-                } else {
-                    panic!("{:?}", Error::AccessDenied);
-                }
+            Err(Error::AccessDenied) if i == 0 => utils::adjust_privileges(),
+            Err(Error::UnsupportedFeatures(f)) => {
+                log::error!("Unsupported features: {:?}, {}", f, utils::feature_hint(f));
+                return ExitCode::FAILURE;
+            }
+            Err(err) => {
+                log::error!("{}", err);
+                return ExitCode::FAILURE;
             }
-            Err(err) => panic!("{:?}", err),
         }
     }
     let mut system = system.unwrap();
+    system.set_cpu_scale(opts.cpu_scale.into());
+
+    // Fail upfront rather than recording a category that can't be sampled
+    for &c in opts.category.iter() {
+        let supported = c.to_process().is_none_or(|c| c.supported())
+            && c.to_system()
+                .is_none_or(|c| c.supported(&mut system, opts.gpu_calc));
+        if !supported {
+            log::error!(
+                "Category {} not supported on this OS",
+                c.to_possible_value().unwrap().get_name()
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+
+    // Categories of a feature missing here are recorded as zeros, say why before that
+    let supported_features = System::supported_features();
+    for feature in [
+        Features::GPU,
+        Features::PROCESS_GPU,
+        Features::CPU_FREQUENCY,
+        Features::FPS,
+        Features::SMC,
+        Features::NET_TRAFFIC,
+        Features::K_OBJECT,
+        Features::ENERGY,
+    ] {
+        if features.contains(feature) && !supported_features.contains(feature) {
+            log::warn!(
+                "{:?} requested but unavailable, {}",
+                feature,
+                utils::feature_hint(feature)
+            );
+        }
+    }
 
     let mut system_metrics = vec![SystemMetrics::default(); sys_category.len()];
+    let system_every = opts
+        .system_interval
+        .map_or(1, |secs| (secs / opts.interval.max(1)).max(1));
+
+    // Series of processes are summed into groups
+    let merged = opts.group_by_name || opts.rollup_to_parent;
+    let (mut groups, mut group_index) = if opts.group_by_name {
+        types::group_by_name(&processes, proc_category.len())
+    } else if opts.rollup_to_parent {
+        types::rollup_to_parent(&system, &processes, proc_category.len())
+    } else {
+        (vec![], vec![])
+    };
 
     let mut last_record_time = Instant::now();
 
-    let outputs = utils::extend_path(&path_re, opts.output);
+    let stdout_json = to_stdout.then(io::stdout);
+    let outputs = utils::extend_path(&path_re, mem::take(&mut opts.output));
     if !utils::check_permission(&outputs) {
-        println!("Permission denied");
-        return;
+        log::error!("Permission denied");
+        return ExitCode::from(EXIT_WRITE_FAILED);
+    }
+
+    // Blocks of the wide layout are per category, rows appended to the file would land under the last one
+    let csv_outputs = outputs
+        .iter()
+        .any(|p| p.extension().is_some_and(|ext| ext == "csv"));
+    if opts.append && csv_outputs && !matches!(opts.csv_layout, CsvLayout::Long) {
+        log::error!("--append to csv outputs needs --csv-layout long");
+        return ExitCode::FAILURE;
     }
 
+    // Existing content of csv outputs, every write starts from it when appending
+    let csv_seeds: HashMap<PathBuf, Vec<u8>> = if opts.append {
+        outputs
+            .iter()
+            .filter(|p| p.extension().is_some_and(|ext| ext == "csv"))
+            .filter_map(|p| Some((p.clone(), fs::read(p).ok().filter(|c| !c.is_empty())?)))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let hostname = sysinfo::System::host_name();
+    let y_max = types::YAxisMax(mem::take(&mut opts.y_max));
+    #[cfg(feature = "embed-assets")]
+    let embed_assets = opts.embed_assets;
+    #[cfg(not(feature = "embed-assets"))]
+    let embed_assets = false;
+
     let write_result = |proc_categories: &[ProcessCategory],
                         sys_categories: &[SystemCategory],
                         timestamps: &[chrono::DateTime<chrono::Local>],
+                        durations: &[f32],
                         processes: &[ProcessInfo],
                         system_metrics: &[SystemMetrics],
                         o: &[PathBuf]| {
-        for output in o.into_iter() {
+        let mut written = true;
+        // Charts and the other outputs stay in M and KBps
+        let display = opts.raw_units.then(|| {
+            types::display_units(proc_categories, sys_categories, processes, system_metrics)
+        });
+        let (display_processes, display_metrics) = display
+            .as_ref()
+            .map_or((processes, system_metrics), |(p, m)| (&p[..], &m[..]));
+
+        for output in o.iter() {
             if let Some(parent) = output.parent() {
-                if parent.components().count() > 0 && !parent.exists() {
-                    fs::create_dir_all(parent).unwrap();
+                if parent.components().count() > 0
+                    && !parent.exists()
+                    && fs::create_dir_all(parent).is_err()
+                {
+                    log::error!("Can't create {}", parent.display());
+                    written = false;
+                    continue;
                 }
             }
 
@@ -138,7 +423,24 @@ fn main() {
                 let swp_file = output.with_extension(utils::SWP_EXTENSION);
                 let mut valid = false;
 
+                // Consumers unwrap their writes, make sure the swap file can be created first
+                if [
+                    "csv", "svg", "json", "db", "sqlite", "txt", "html", "md", "lp", "plt",
+                ]
+                .iter()
+                .any(|e| ext == *e)
+                    && fs::File::create(&swp_file).is_err()
+                {
+                    log::error!("Can't write to {}", output.display());
+                    written = false;
+                    continue;
+                }
+
                 if ext == "csv" {
+                    let seed = csv_seeds.get(output);
+                    if let Some(seed) = seed {
+                        fs::write(&swp_file, seed).unwrap();
+                    }
                     consumer_csv::consume(
                         &swp_file,
                         proc_categories,
@@ -146,6 +448,13 @@ fn main() {
                         timestamps,
                         processes,
                         system_metrics,
+                        consumer_csv::Options {
+                            append: seed.is_some(),
+                            layout: opts.csv_layout,
+                            timestamp: opts.timestamp,
+                            precision: opts.precision,
+                            raw_units: opts.raw_units,
+                        },
                     );
                     valid = true;
                 } else if ext == "svg" {
@@ -154,8 +463,16 @@ fn main() {
                         proc_categories,
                         sys_categories,
                         timestamps,
-                        processes,
-                        system_metrics,
+                        display_processes,
+                        display_metrics,
+                        consumer_svg::Layout {
+                            width: opts.svg_width,
+                            height: opts.svg_height,
+                            y_max: &y_max,
+                            command_width: opts.command_width,
+                            durations,
+                            smoothing: Smoothing::new(opts.smooth, opts.smooth_mode),
+                        },
                     );
                     valid = true;
                 } else if ext == "json" {
@@ -166,6 +483,68 @@ fn main() {
                         timestamps,
                         processes,
                         system_metrics,
+                        consumer_json::Meta {
+                            interval_secs: opts.interval,
+                            hostname: hostname.as_deref(),
+                            sample_durations_secs: durations,
+                            timestamp: opts.timestamp,
+                            raw_units: opts.raw_units,
+                            summary_only: opts.summary_only,
+                        },
+                    );
+                    valid = true;
+                } else if ext == "db" || ext == "sqlite" {
+                    // The swap file may be left from an interrupted write
+                    let _ = fs::remove_file(&swp_file);
+                    consumer_sqlite::consume(
+                        &swp_file,
+                        proc_categories,
+                        sys_categories,
+                        timestamps,
+                        display_processes,
+                        display_metrics,
+                    );
+                    valid = true;
+                } else if ext == "txt" {
+                    consumer_txt::consume(
+                        &swp_file,
+                        proc_categories,
+                        sys_categories,
+                        timestamps,
+                        display_processes,
+                        display_metrics,
+                    );
+                    valid = true;
+                } else if ext == "lp" {
+                    consumer_influx::consume(
+                        &swp_file,
+                        proc_categories,
+                        sys_categories,
+                        timestamps,
+                        display_processes,
+                        display_metrics,
+                    );
+                    valid = true;
+                } else if ext == "md" {
+                    consumer_md::consume(
+                        &swp_file,
+                        proc_categories,
+                        sys_categories,
+                        timestamps,
+                        display_processes,
+                        display_metrics,
+                    );
+                    valid = true;
+                } else if ext == "plt" {
+                    // The script references the data file next to it
+                    consumer_gnuplot::consume(
+                        &swp_file,
+                        output.with_extension("dat"),
+                        proc_categories,
+                        sys_categories,
+                        timestamps,
+                        display_processes,
+                        display_metrics,
                     );
                     valid = true;
                 } else if ext == "html" {
@@ -174,18 +553,35 @@ fn main() {
                         proc_categories,
                         sys_categories,
                         timestamps,
-                        processes,
-                        system_metrics,
+                        display_processes,
+                        display_metrics,
+                        consumer_html::Options {
+                            embed_assets,
+                            y_max: &y_max,
+                            durations,
+                            smoothing: Smoothing::new(opts.smooth, opts.smooth_mode),
+                            link_zoom: opts.link_zoom,
+                        },
                     );
                     valid = true;
                 }
 
                 if valid {
-                    fs::rename(swp_file, output).unwrap();
-                    println!("Write to {}\r", output.display());
+                    // A power loss must leave either the old output or the complete new one
+                    if utils::sync_file(&swp_file).is_ok() && fs::rename(swp_file, output).is_ok() {
+                        if let Err(err) = utils::sync_parent_dir(output) {
+                            log::warn!("Can't sync the directory of {}: {}", output.display(), err);
+                        }
+                        log::info!("Write to {}", output.display());
+                    } else {
+                        log::error!("Can't write to {}", output.display());
+                        written = false;
+                    }
                 }
             }
         }
+
+        written
     };
 
     let mut prompt = None;
@@ -195,25 +591,78 @@ fn main() {
     }
 
     if let Some(prompt) = &mut prompt {
-        if !utils::overwrite_detect(&outputs, prompt) {
-            return;
+        let appended = |p: &PathBuf| {
+            csv_seeds.contains_key(p)
+                || (opts.append && p.extension().is_some_and(|ext| ext == "ndjson"))
+        };
+        let overwritten: Vec<_> = outputs.iter().filter(|p| !appended(p)).cloned().collect();
+        if !utils::overwrite_detect(&overwritten, prompt) {
+            return ExitCode::SUCCESS;
         }
     }
 
+    // Streaming outputs, written on every sample
+    let mut ndjson_files: Vec<_> = outputs
+        .iter()
+        .filter(|p| p.extension().is_some_and(|ext| ext == "ndjson"))
+        .map(|p| {
+            if let Some(parent) = p.parent() {
+                if parent.components().count() > 0 && !parent.exists() {
+                    fs::create_dir_all(parent).unwrap();
+                }
+            }
+            fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(opts.append)
+                .truncate(!opts.append)
+                .open(p)
+                .unwrap()
+        })
+        .collect();
+
+    // Whether any process or system category got a sample
+    let mut any_valid = false;
+
     let mut end_time = None;
+    let mut last_save = Instant::now();
     let shutdown = Arc::new(AtomicBool::new(false));
     for &sig in signal_hook::consts::TERM_SIGNALS {
         if let Err(err) = signal_hook::flag::register(sig, shutdown.clone()) {
-            eprintln!("signal_hook({sig}): {err}\r");
+            log::warn!("signal_hook({sig}): {err}");
         }
     }
 
+    if opts.once {
+        // Throwaway refresh, cpu usage needs two of them to be computed
+        let _ = system.update(Instant::now());
+        last_record_time = Instant::now();
+    }
+
+    // Printed regardless of `-v`, for supervisors watching a quiet recording
+    let mut last_heartbeat = Instant::now();
+    let mut heartbeat = |samples: isize| {
+        if let Some(every) = opts.heartbeat {
+            if last_heartbeat.elapsed() >= *every {
+                last_heartbeat = Instant::now();
+                eprintln!(
+                    "{} [precord] alive, {} samples\r",
+                    chrono::Local::now().to_rfc3339(),
+                    samples.max(0)
+                );
+            }
+        }
+    };
+
     for i in -(opts.skip as isize).. {
         let mut command_mode = false;
+        heartbeat(i);
 
         loop {
             let delay = if command_mode {
                 None
+            } else if opts.once {
+                Some(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL)
             } else {
                 let now = Instant::now();
                 let since = now.saturating_duration_since(last_record_time);
@@ -231,35 +680,51 @@ fn main() {
                         let p = utils::extend_path(&path_re, p);
                         let p = if !p.is_empty() { &p } else { &outputs };
                         if utils::check_permission(p) {
-                            write_result(
+                            let _ = write_result(
                                 &proc_category,
                                 &sys_category,
                                 &timestamps,
-                                &processes,
+                                &durations,
+                                if merged { &groups } else { &processes },
                                 &system_metrics,
                                 p,
                             );
                         } else {
-                            println!("Permission denied\r");
+                            log::error!("Permission denied");
                         }
                         command_mode = true;
                     }
-                    utils::Command::Quit => return,
+                    utils::Command::Quit => return ExitCode::SUCCESS,
+                    // Keep what was recorded, like a SIGINT outside of interactive mode
+                    utils::Command::Interrupt if !outputs.is_empty() => {
+                        let written = write_result(
+                            &proc_category,
+                            &sys_category,
+                            &timestamps,
+                            &durations,
+                            if merged { &groups } else { &processes },
+                            &system_metrics,
+                            &outputs,
+                        );
+                        return exit_code(written, any_valid);
+                    }
+                    utils::Command::Interrupt => return ExitCode::SUCCESS,
                     utils::Command::WriteThenQuit(p) => {
                         let p = utils::extend_path(&path_re, p);
                         let p = if !p.is_empty() { &p } else { &outputs };
                         if utils::check_permission(p) {
-                            write_result(
+                            let written = write_result(
                                 &proc_category,
                                 &sys_category,
                                 &timestamps,
-                                &processes,
+                                &durations,
+                                if merged { &groups } else { &processes },
                                 &system_metrics,
                                 p,
                             );
-                            return;
+                            return exit_code(written, any_valid);
                         } else {
-                            println!("Permission denied\r");
+                            log::error!("Permission denied");
                             command_mode = true;
                         }
                     }
@@ -273,7 +738,16 @@ fn main() {
                     | utils::Command::Unknown => command_mode = true,
                 }
             } else if let Some(delay) = delay {
-                thread::sleep(delay);
+                // Sleep in slices so a signal doesn't wait for the whole interval
+                let deadline = Instant::now() + delay;
+                while !shutdown.load(std::sync::atomic::Ordering::Acquire) {
+                    heartbeat(i);
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        break;
+                    }
+                    thread::sleep(left.min(Duration::from_millis(100)));
+                }
                 break;
             } else {
                 unreachable!();
@@ -282,9 +756,15 @@ fn main() {
 
         last_record_time = Instant::now();
 
-        system.update(last_record_time);
+        // Sources failing this time keep their previous values
+        if let Err(err) = system.update(last_record_time) {
+            log::warn!("{}", paint(format!("Update Lost: {}", err), Color::DarkRed));
+        }
 
         if i < 0 {
+            if shutdown.load(std::sync::atomic::Ordering::Acquire) {
+                break;
+            }
             continue;
         }
 
@@ -292,63 +772,252 @@ fn main() {
             end_time = opts
                 .time
                 .map(|d| chrono::Local::now() + chrono::Duration::from_std(*d).unwrap());
-        } else if let Some(auto_save) = opts.auto_save {
-            if i as u64 % auto_save == 0 {
-                write_result(
+            last_save = last_record_time;
+        } else {
+            // Counted and timed auto savings coexist, either one triggers a save
+            let by_count = opts
+                .auto_save
+                .is_some_and(|auto_save| i as u64 % auto_save == 0);
+            let by_time = opts
+                .auto_save_secs
+                .is_some_and(|d| last_record_time.duration_since(last_save) >= *d);
+            if by_count || by_time {
+                last_save = last_record_time;
+                let _ = write_result(
                     &proc_category,
                     &sys_category,
                     &timestamps,
-                    &processes,
+                    &durations,
+                    if merged { &groups } else { &processes },
                     &system_metrics,
                     &outputs,
                 );
             }
         }
 
+        if opts.follow_name || opts.lists_pids() {
+            let mut started = vec![];
+
+            if opts.follow_name {
+                let ended: Vec<_> = processes
+                    .iter()
+                    .filter(|p| system.process_name(p.pid).is_none())
+                    .map(|p| p.pid)
+                    .collect();
+                system.remove_pids(ended);
+
+                started = opts.follow_processes(&system, &processes, proc_category.len());
+            }
+
+            if let Some(pids) = opts.listed_pids() {
+                // Dropped from the list, recorded as zeros until it's listed again
+                let dropped: Vec<_> = processes
+                    .iter()
+                    .map(|p| p.pid)
+                    .filter(|pid| {
+                        listed_pids.contains(pid) && !pids.contains(pid) && !cli_pids.contains(pid)
+                    })
+                    .collect();
+                system.remove_pids(dropped.iter().copied());
+                paused.extend(dropped);
+
+                let resumed: Vec<_> = paused
+                    .iter()
+                    .copied()
+                    .filter(|pid| pids.contains(pid))
+                    .collect();
+                for pid in resumed.iter() {
+                    paused.remove(pid);
+                }
+                system.add_pids(resumed);
+
+                for &pid in pids.iter() {
+                    if processes.iter().chain(started.iter()).any(|p| p.pid == pid) {
+                        continue;
+                    }
+                    if let Some(process) = ProcessInfo::new(&system, proc_category.len(), pid)
+                        .filter(|p| !opts.excluded(p))
+                    {
+                        started.push(process);
+                    }
+                }
+                listed_pids = pids;
+            }
+
+            let ancestry = opts.rollup_to_parent.then(|| types::Ancestry::new(&system));
+            let pids: HashSet<_> = processes
+                .iter()
+                .chain(started.iter())
+                .map(|p| p.pid)
+                .collect();
+
+            for process in started.iter_mut() {
+                // Leading zeros keep every series the same length
+                for values in process.values.iter_mut() {
+                    values.resize(timestamps.len(), 0.0);
+                }
+
+                if merged {
+                    let gi = if let Some(ancestry) = &ancestry {
+                        let root = ancestry.root(&system, &pids, process.pid);
+                        groups.iter().position(|g| g.pid == root)
+                    } else {
+                        groups.iter().position(|g| g.name == process.name)
+                    };
+                    let gi = match gi {
+                        Some(gi) => gi,
+                        None => {
+                            let (mut group, _) = types::group_by_name(
+                                std::slice::from_ref(process),
+                                proc_category.len(),
+                            );
+                            groups.append(&mut group);
+                            groups.len() - 1
+                        }
+                    };
+                    for values in groups[gi].values.iter_mut() {
+                        values.resize(timestamps.len(), 0.0);
+                    }
+                    group_index.push(gi);
+                }
+
+                log::info!("Follow {}({})", process.name, process.pid);
+            }
+            system.add_pids(started.iter().map(|p| p.pid));
+            processes.append(&mut started);
+        }
+
         // Process
         if !proc_category.is_empty() {
-            for process in processes.iter_mut() {
+            for group in groups.iter_mut() {
+                group.valid = false;
+                for values in group.values.iter_mut() {
+                    values.push(0.0);
+                }
+            }
+
+            for (pi, process) in processes.iter_mut().enumerate() {
+                if process.ended.is_none() && system.process_name(process.pid).is_none() {
+                    process.ended = Some(timestamps.len());
+                    process.valid = false;
+                    log::info!("{}({}) exited", process.name, process.pid);
+                }
+                if process.ended.is_some() || paused.contains(&process.pid) {
+                    for values in process.values.iter_mut() {
+                        values.push(0.0);
+                    }
+                    continue;
+                }
+
                 let mut message = format!("{}({})", &process.name, process.pid);
 
                 for (idx, &c) in proc_category.iter().enumerate() {
-                    if let Some(v) = c.sample(&mut system, opts.gpu_calc, process.pid) {
+                    if let Some(v) =
+                        c.sample(&mut system, opts.gpu_calc, process.pid, opts.raw_units)
+                    {
+                        let v = baseline.as_ref().map_or(v, |b| b.process(c, v));
                         process.valid = true;
+                        any_valid = true;
                         process.values[idx].push(v);
+                        let precision = opts.precision.unwrap_or(c.precision());
+                        let (_, scale) = c.output_unit(opts.raw_units);
                         message.push_str(&format!(
                             " / {}",
-                            format!("{:?} {:.2}{}", c, v, c.unit()).with(c.color())
+                            paint(
+                                format!("{:?} {:.*}{}", c, precision, v / scale, c.unit()),
+                                c.color()
+                            )
                         ));
+
+                        if let Some(&gi) = group_index.get(pi) {
+                            groups[gi].valid = true;
+                            *groups[gi].values[idx].last_mut().unwrap() += v;
+                        }
                     } else {
                         process.valid = false;
                         process.values[idx].push(0.0);
-                        message.push_str(&format!(" / {}", format!("{:?} Lost", c).dark_red()));
+                        message.push_str(&format!(
+                            " / {}",
+                            paint(format!("{:?} Lost", c), Color::DarkRed)
+                        ));
                     }
                 }
 
-                println!("{}\r", message);
+                if !merged {
+                    report(&message);
+                }
+            }
+
+            for (gi, group) in groups.iter().enumerate() {
+                let count = group_index.iter().filter(|&&i| i == gi).count();
+                let mut message = format!("{}({} pids)", &group.name, count);
+
+                for (idx, &c) in proc_category.iter().enumerate() {
+                    let v = group.values[idx].last().copied().unwrap_or(0.0);
+                    let precision = opts.precision.unwrap_or(c.precision());
+                    let (_, scale) = c.output_unit(opts.raw_units);
+                    message.push_str(&format!(
+                        " / {}",
+                        paint(
+                            format!("{:?} {:.*}{}", c, precision, v / scale, c.unit()),
+                            c.color()
+                        )
+                    ));
+                }
+
+                report(&message);
             }
         }
 
-        // System
-        for (idx, &c) in sys_category.iter().enumerate() {
-            let rows = c.sample(&mut system, opts.gpu_calc);
+        // System, rows are forward-filled between samples of `--system-interval`
+        if i as u64 % system_every == 0 {
+            for (idx, &c) in sys_category.iter().enumerate() {
+                let mut rows =
+                    c.sample(&mut system, opts.gpu_calc, opts.gpu_engines, opts.raw_units);
+                any_valid |= !rows.is_empty();
+                if let Some(baseline) = &baseline {
+                    baseline.system(c, &mut rows);
+                }
 
-            println!(
-                "{:?}: [{}]\r",
-                c,
-                rows.iter()
-                    .map(|f| format!("{:.2}{}", f, c.unit()).with(c.color()).to_string())
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            );
+                let precision = opts.precision.unwrap_or(c.precision());
+                let (_, scale) = c.output_unit(opts.raw_units);
+                report(&format!(
+                    "{:?}: [{}]",
+                    c,
+                    rows.iter()
+                        .map(|f| {
+                            paint(
+                                format!("{:.*}{}", precision, f / scale, c.unit()),
+                                c.color(),
+                            )
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ));
 
-            let metrics = &mut system_metrics[idx];
+                let metrics = &mut system_metrics[idx];
 
-            if metrics.rows.is_empty() {
-                metrics.rows = rows.into_iter().map(|row| vec![row]).collect();
-            } else {
-                for (row, v) in metrics.rows.iter_mut().zip(rows.into_iter()) {
-                    row.push(v);
+                if metrics.rows.is_empty() {
+                    metrics.labels = c.row_labels(&mut system, opts.gpu_calc, opts.gpu_engines);
+                    metrics.rows = rows.into_iter().map(|row| vec![row]).collect();
+                } else {
+                    // Rows showing up later, e.g., engines of `--gpu-engines`, start with zeros
+                    if rows.len() > metrics.rows.len() {
+                        let samples = metrics.rows[0].len();
+                        metrics.labels = c.row_labels(&mut system, opts.gpu_calc, opts.gpu_engines);
+                        metrics.rows.resize(rows.len(), vec![0.; samples]);
+                    }
+                    for (row, v) in metrics.rows.iter_mut().zip(rows) {
+                        row.push(v);
+                    }
+                }
+            }
+        } else {
+            for metrics in system_metrics.iter_mut() {
+                for row in metrics.rows.iter_mut() {
+                    if let Some(&v) = row.last() {
+                        row.push(v);
+                    }
                 }
             }
         }
@@ -363,12 +1032,72 @@ fn main() {
             let _ = write!(&mut progress, " / {}", end_time);
         }
 
-        println!("{}\r", progress);
+        if !opts.once && opts.progress != Progress::Off {
+            log::info!("{}", progress);
+        }
 
         // let _ = utils::drain_filter_vec(&mut processes, |p| !p.valid);
 
         let now = chrono::Local::now();
         timestamps.push(now);
+        let duration = system.last_sample_duration();
+        durations.push(duration.as_secs_f32());
+        if duration > Duration::from_secs(opts.interval) * 3 / 2 {
+            log::debug!("Sample {} stalled: {:?}", i + 1, duration);
+        }
+
+        if let Some(stdout) = &stdout_json {
+            consumer_ndjson::consume(
+                stdout.lock(),
+                &proc_category,
+                &sys_category,
+                if merged { &groups } else { &processes },
+                &system_metrics,
+                consumer_ndjson::Meta {
+                    timestamp: opts.timestamp.format(&now),
+                    duration,
+                    raw_units: opts.raw_units,
+                },
+            );
+        }
+
+        for file in ndjson_files.iter_mut() {
+            consumer_ndjson::consume(
+                file,
+                &proc_category,
+                &sys_category,
+                if merged { &groups } else { &processes },
+                &system_metrics,
+                consumer_ndjson::Meta {
+                    timestamp: opts.timestamp.format(&now),
+                    duration,
+                    raw_units: opts.raw_units,
+                },
+            );
+        }
+
+        if let Some(window) = opts.window {
+            let window = window as usize;
+            types::keep_last(&mut timestamps, window);
+            types::keep_last(&mut durations, window);
+            for p in processes.iter_mut().chain(groups.iter_mut()) {
+                let dropped = p
+                    .values
+                    .first()
+                    .map_or(0, |v| v.len().saturating_sub(window));
+                for values in p.values.iter_mut() {
+                    types::keep_last(values, window);
+                }
+                if let Some(ended) = &mut p.ended {
+                    *ended = ended.saturating_sub(dropped);
+                }
+            }
+            for metrics in system_metrics.iter_mut() {
+                for row in metrics.rows.iter_mut() {
+                    types::keep_last(row, window);
+                }
+            }
+        }
 
         if let Some(count) = opts.count {
             if i + 1 >= count as isize {
@@ -387,12 +1116,25 @@ fn main() {
         }
     }
 
-    write_result(
+    let written = write_result(
         &proc_category,
         &sys_category,
         &timestamps,
-        &processes,
+        &durations,
+        if merged { &groups } else { &processes },
         &system_metrics,
         &outputs,
     );
+    exit_code(written, any_valid)
+}
+
+fn exit_code(written: bool, any_valid: bool) -> ExitCode {
+    if !written {
+        ExitCode::from(EXIT_WRITE_FAILED)
+    } else if !any_valid {
+        log::error!("No samples recorded");
+        ExitCode::from(EXIT_NO_SAMPLES)
+    } else {
+        ExitCode::SUCCESS
+    }
 }
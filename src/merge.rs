@@ -0,0 +1,275 @@
+use crate::consumer_json::SCHEMA_VERSION;
+use crate::opt::{Opts, ProcessCategory, SystemCategory};
+use crate::types::{ProcessInfo, Smoothing, SystemMetrics, Timestamp, YAxisMax};
+use crate::{consumer_html, consumer_svg, Pid};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Renders `.json` captures into one chart per category, series are labelled by the file they come from
+pub fn merge(inputs: &[PathBuf], outputs: &[PathBuf], opts: &Opts) -> Result<(), String> {
+    let captures = inputs
+        .iter()
+        .map(|path| Capture::read(path).map_err(|err| format!("{}: {}", path.display(), err)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Categories recorded by every capture
+    let mut proc_category: Vec<_> = captures[0].process_records.keys().copied().collect();
+    proc_category.retain(|c| {
+        captures
+            .iter()
+            .all(|cap| cap.process_records.contains_key(c))
+    });
+    proc_category.sort();
+    let mut sys_category: Vec<_> = captures[0].sys_records.keys().copied().collect();
+    sys_category.retain(|c| captures.iter().all(|cap| cap.sys_records.contains_key(c)));
+    sys_category.sort();
+
+    // Captures are overlaid sample by sample, on the time axis of the first one
+    let len = captures.iter().map(|cap| cap.len()).min().unwrap_or(0);
+    if captures.iter().any(|cap| cap.len() != len) {
+        log::warn!("Captures are cut to {} samples, the shortest of them", len);
+    }
+    let timestamps: Vec<_> = captures[0]
+        .timestamps()
+        .into_iter()
+        .take(len)
+        .collect::<Result<_, _>>()?;
+
+    let durations: Vec<_> = captures[0]
+        .sample_durations_secs
+        .iter()
+        .copied()
+        .take(len)
+        .collect();
+
+    let mut processes: Vec<ProcessInfo> = vec![];
+    let mut system_metrics = vec![SystemMetrics::default(); sys_category.len()];
+    for (path, cap) in inputs.iter().zip(captures.iter()) {
+        let source = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        // Records of a process are listed under every category
+        let base = processes.len();
+        let mut pids: Vec<Pid> = vec![];
+        for (ci, c) in proc_category.iter().enumerate() {
+            for record in cap.process_records[c].iter() {
+                let pi = match pids.iter().position(|&pid| pid == record.pid) {
+                    Some(pi) => pi,
+                    None => {
+                        pids.push(record.pid);
+                        processes.push(ProcessInfo {
+                            pid: record.pid,
+                            name: format!("{}: {}", source, record.name),
+                            command: record.command.clone(),
+                            exe: record.exe.clone(),
+                            start_time: None,
+                            values: vec![vec![0.; len]; proc_category.len()],
+                            valid: true,
+                            ended: None,
+                        });
+                        pids.len() - 1
+                    }
+                };
+                processes[base + pi].values[ci] = record.values(len);
+            }
+        }
+
+        for (i, c) in sys_category.iter().enumerate() {
+            let metrics = &mut system_metrics[i];
+            for (ri, record) in cap.sys_records[c].iter().enumerate() {
+                let label = record
+                    .label
+                    .clone()
+                    .unwrap_or_else(|| format!("{:?}{}", c, ri));
+                metrics.labels.push(format!("{}: {}", source, label));
+                metrics.rows.push(record.values(len));
+            }
+        }
+    }
+
+    let y_max = YAxisMax(opts.y_max.clone());
+    #[cfg(feature = "embed-assets")]
+    let embed_assets = opts.embed_assets;
+    #[cfg(not(feature = "embed-assets"))]
+    let embed_assets = false;
+
+    for output in outputs {
+        match output.extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => consumer_svg::consume(
+                output,
+                &proc_category,
+                &sys_category,
+                &timestamps,
+                &processes,
+                &system_metrics,
+                consumer_svg::Layout {
+                    width: opts.svg_width,
+                    height: opts.svg_height,
+                    y_max: &y_max,
+                    command_width: opts.command_width,
+                    durations: &durations,
+                    smoothing: Smoothing::new(opts.smooth, opts.smooth_mode),
+                },
+            ),
+            Some("html") => consumer_html::consume(
+                output,
+                &proc_category,
+                &sys_category,
+                &timestamps,
+                &processes,
+                &system_metrics,
+                consumer_html::Options {
+                    embed_assets,
+                    y_max: &y_max,
+                    durations: &durations,
+                    smoothing: Smoothing::new(opts.smooth, opts.smooth_mode),
+                    link_zoom: opts.link_zoom,
+                },
+            ),
+            _ => return Err(format!("{}: only .svg/.html are merged", output.display())),
+        }
+        log::info!("Write to {}", output.display());
+    }
+
+    Ok(())
+}
+
+// The `.json` output of a recording
+#[derive(Deserialize)]
+pub(crate) struct Capture {
+    version: String,
+    #[serde(default)]
+    summary_only: bool,
+    // Missing in older captures, totals are left out then
+    #[serde(default)]
+    sample_durations_secs: Vec<f32>,
+    // Missing in older captures, values are in the units of categories then
+    #[serde(default)]
+    process_units: HashMap<ProcessCategory, String>,
+    #[serde(default)]
+    sys_units: HashMap<SystemCategory, String>,
+    pub(crate) process_records: HashMap<ProcessCategory, Vec<ProcessRecord>>,
+    pub(crate) sys_records: HashMap<SystemCategory, Vec<SystemRecord>>,
+}
+
+impl Capture {
+    pub(crate) fn read(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut capture: Self = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+        if capture.version != SCHEMA_VERSION {
+            return Err(format!("unsupported version {}", capture.version));
+        }
+        if capture.summary_only {
+            return Err("captures of --summary-only carry no samples".to_string());
+        }
+
+        // Captures taken with `--raw-units` are turned back into M and KBps
+        for (c, records) in capture.process_records.iter_mut() {
+            let scale = match capture.process_units.get(c) {
+                Some(unit) => raw_scale(unit, c.unit(), c.output_unit(true))?,
+                None => continue,
+            };
+            for r in records.iter_mut().flat_map(|p| p.records.iter_mut()) {
+                r.value /= scale;
+            }
+        }
+        for (c, records) in capture.sys_records.iter_mut() {
+            let scale = match capture.sys_units.get(c) {
+                Some(unit) => raw_scale(unit, c.unit(), c.output_unit(true))?,
+                None => continue,
+            };
+            for r in records.iter_mut().flat_map(|s| s.records.iter_mut()) {
+                r.value /= scale;
+            }
+        }
+
+        Ok(capture)
+    }
+
+    // Samples of the capture, every record carries all of them
+    fn records(&self) -> Option<&[Record]> {
+        self.process_records
+            .values()
+            .flatten()
+            .map(|p| p.records.as_slice())
+            .chain(
+                self.sys_records
+                    .values()
+                    .flatten()
+                    .map(|s| s.records.as_slice()),
+            )
+            .next()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.records().map_or(0, |r| r.len())
+    }
+
+    fn timestamps(&self) -> Vec<Result<chrono::DateTime<chrono::Local>, String>> {
+        self.records()
+            .unwrap_or_default()
+            .iter()
+            .map(|r| match &r.timestamp {
+                Timestamp::Text(t) => chrono::DateTime::parse_from_rfc3339(t)
+                    .map(|t| t.with_timezone(&chrono::Local))
+                    .map_err(|err| format!("timestamp {}: {}", t, err)),
+                Timestamp::Millis(ms) => chrono::DateTime::from_timestamp_millis(*ms)
+                    .map(|t| t.with_timezone(&chrono::Local))
+                    .ok_or_else(|| format!("timestamp {}", ms)),
+            })
+            .collect()
+    }
+}
+
+// Factor of values recorded in `unit`, either the unit of the category or its raw one
+fn raw_scale(unit: &str, category_unit: &str, (raw, scale): (&str, f32)) -> Result<f32, String> {
+    if unit == category_unit {
+        Ok(1.)
+    } else if unit == raw {
+        Ok(scale)
+    } else {
+        Err(format!("unexpected unit {}", unit))
+    }
+}
+
+#[derive(Deserialize)]
+struct Record {
+    timestamp: Timestamp,
+    value: f32,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ProcessRecord {
+    pid: Pid,
+    name: String,
+    command: String,
+    #[serde(default)]
+    exe: Option<PathBuf>,
+    // Missing in captures of `--summary-only`
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+impl ProcessRecord {
+    pub(crate) fn values(&self, len: usize) -> Vec<f32> {
+        self.records.iter().take(len).map(|r| r.value).collect()
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SystemRecord {
+    label: Option<String>,
+    // Missing in captures of `--summary-only`
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+impl SystemRecord {
+    pub(crate) fn values(&self, len: usize) -> Vec<f32> {
+        self.records.iter().take(len).map(|r| r.value).collect()
+    }
+}
@@ -1,26 +1,43 @@
-use crate::types::ProcessInfo;
+use crate::types::{ProcessInfo, Timestamp};
 use crate::Pid;
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Parser, Subcommand, ValueEnum};
 use crossterm::style::Color;
-use precord_core::{platform, Features, System};
-use serde::Serialize;
-use std::collections::HashSet;
+use precord_core::{platform, Error, Features, ProcessStatus, System};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::iter;
 use std::path::PathBuf;
-use sysinfo::ProcessStatus;
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub struct Opts {
     #[arg(short, long, num_args(..))]
-    process: Vec<Pid>,
+    pub process: Vec<Pid>,
     #[arg(long, num_args(..))]
     name: Vec<String>,
-    /// Specify the output file, e.g., -o result.{svg,html,json,csv}
+    /// Regex matched against the command line of processes
+    #[arg(long, num_args(..))]
+    cmdline: Vec<Regex>,
+    /// Full path of the executable of processes, e.g., to tell apart binaries with the same name
+    #[arg(long, num_args(..))]
+    exe: Vec<PathBuf>,
+    /// Skip processes whose name or command line contains the substring
+    #[arg(long, num_args(..))]
+    exclude: Vec<String>,
+    /// Select the N processes with the highest CPU usage once at startup,
+    /// used when no process, name, cmdline or exe is given
+    #[arg(long)]
+    pub top: Option<usize>,
+    /// Specify the output file, e.g., -o result.{svg,html,json,csv,ndjson,db,txt}
     #[arg(short, long, value_parser, num_args(..))]
     pub output: Vec<PathBuf>,
     #[arg(short, long, default_value_t = 1)]
     pub interval: u64,
+    /// Interval for recording system categories, rounded down to a multiple of --interval
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub system_interval: Option<u64>,
     #[arg(short = 'n')]
     pub count: Option<usize>,
     /// Recording time limit, e.g., --time 1h30m59s
@@ -34,12 +51,119 @@ pub struct Opts {
     pub skip: usize,
     #[arg(long, value_enum, default_value = "max")]
     pub gpu_calc: GpuCalculation,
-    /// Interactive mode
+    /// 100% of cpu is one core with per-core, all physical cores with normalized
+    #[arg(long, value_enum, default_value = "per-core")]
+    pub cpu_scale: CpuScale,
+    /// Add a row per engine type, e.g., 3D or VideoEncode, to sys_gpu, Windows only
+    #[arg(long)]
+    pub gpu_engines: bool,
+    /// Interactive mode, off when stdin isn't a terminal
     #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
     pub interactive: bool,
+    /// Same as --interactive false
+    #[arg(long, conflicts_with = "interactive")]
+    pub no_interactive: bool,
     /// Interval of auto saving
     #[arg(long)]
     pub auto_save: Option<u64>,
+    /// Time between auto savings, e.g., --auto-save-secs 5m, along with --auto-save whichever comes first
+    #[arg(long, value_parser)]
+    pub auto_save_secs: Option<humantime::Duration>,
+    /// Append to existing csv/ndjson outputs instead of overwriting them, csv outputs need --csv-layout long
+    #[arg(long)]
+    pub append: bool,
+    /// Layout of csv outputs
+    #[arg(long, value_enum, default_value = "wide")]
+    pub csv_layout: CsvLayout,
+    /// Decimal places of values in csv outputs and the terminal, defaults depend on the category
+    #[arg(long)]
+    pub precision: Option<usize>,
+    /// Record bytes and bytes per second in csv/json outputs instead of M and KBps, the terminal is unchanged
+    #[arg(long)]
+    pub raw_units: bool,
+    /// Write only the stats of every process and system row to json outputs, without samples
+    #[arg(long)]
+    pub summary_only: bool,
+    /// Format of timestamps in csv/json/ndjson outputs
+    #[arg(long, value_enum, default_value = "rfc3339")]
+    pub timestamp: TimestampFormat,
+    /// Merge processes sharing the same name into one summed series
+    #[arg(long)]
+    pub group_by_name: bool,
+    /// Sum children found by -r into their topmost recorded ancestor, only the roots are written
+    #[arg(long, requires = "recurse_children", conflicts_with = "group_by_name")]
+    pub rollup_to_parent: bool,
+    /// Width of svg output
+    #[arg(long, default_value_t = 1280)]
+    pub svg_width: u32,
+    /// Height of every category chart in svg output
+    #[arg(long, default_value_t = 720)]
+    pub svg_height: u32,
+    /// Shorten commands in svg labels to this many characters, json and html tooltips keep them whole
+    #[arg(long)]
+    pub command_width: Option<usize>,
+    /// Re-scan `--name` on every sample to record processes started later
+    #[arg(long)]
+    pub follow_name: bool,
+    /// Read newline separated pids from a file, re-read on every sample to follow its changes
+    #[arg(long)]
+    pub pid_file: Option<PathBuf>,
+    /// Record processes of a cgroup, e.g., system.slice/docker-<id>.scope, re-read on every sample
+    #[cfg(target_os = "linux")]
+    #[arg(long)]
+    pub cgroup: Option<PathBuf>,
+    /// Record processes of a named job object, re-read on every sample
+    #[cfg(target_os = "windows")]
+    #[arg(long)]
+    pub job: Option<String>,
+    /// Record a running app by name or bundle id, e.g., Safari or com.apple.Safari, with the helpers it's
+    /// responsible for, re-read on every sample
+    #[cfg(target_os = "macos")]
+    #[arg(long)]
+    pub app: Option<String>,
+    /// Inline the echarts bundle into html outputs so they render offline
+    #[cfg(feature = "embed-assets")]
+    #[arg(long)]
+    pub embed_assets: bool,
+    /// Zoom and pan the charts of html outputs together
+    #[arg(long)]
+    pub link_zoom: bool,
+    /// Fixed top of the y-axis of a category in svg/html outputs, e.g., --y-max cpu=3200
+    #[arg(long, value_parser = parse_y_max, num_args(..))]
+    pub y_max: Vec<(Category, f32)>,
+    /// Plot the moving average over this many samples in svg/html outputs, e.g., --smooth 10
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub smooth: Option<u64>,
+    /// Lines plotted with --smooth
+    #[arg(long, value_enum, default_value = "both", requires = "smooth")]
+    pub smooth_mode: SmoothMode,
+    /// Keep only the last N samples in memory and outputs, for long-lived captures
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub window: Option<u64>,
+    /// Print a single sample of every category and exit, outputs are only written when given
+    #[arg(long, conflicts_with_all = ["count", "time", "skip", "auto_save", "auto_save_secs"])]
+    pub once: bool,
+    /// Print `pid name command` of the selected processes and exit without sampling
+    #[arg(long, conflicts_with = "once")]
+    pub list: bool,
+    /// Print an alive line to stderr at this period, e.g., --heartbeat 1m
+    #[arg(long, value_parser)]
+    pub heartbeat: Option<humantime::Duration>,
+    /// Progress logged on every sample
+    #[arg(long, value_enum, default_value = "full")]
+    pub progress: Progress,
+    /// Color the terminal output, `auto` only when it is a terminal and `NO_COLOR` is unset
+    #[arg(long, value_enum, default_value = "auto")]
+    pub color: ColorChoice,
+    /// Log sampling progress with -v, debug details with -vv, `RUST_LOG` takes precedence
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Load options from a toml file, flags on the command line take precedence
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Subtract the means of an idle `.json` capture from every sample, see `baseline capture`
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
     #[command(subcommand)]
     pub action: Option<Action>,
 }
@@ -48,7 +172,16 @@ impl Opts {
     pub fn find_processes(&self, system: &System, proc_category_len: usize) -> Vec<ProcessInfo> {
         let mut processes: Vec<ProcessInfo> = vec![];
 
-        if self.name.is_empty() {
+        let exe = self.exe_paths();
+        if self.process.is_empty()
+            && self.name.is_empty()
+            && self.cmdline.is_empty()
+            && exe.is_empty()
+        {
+            if let Some(top) = self.top {
+                processes = self.top_processes(system, top, proc_category_len);
+            }
+        } else if self.name.is_empty() && self.cmdline.is_empty() && exe.is_empty() {
             for &pid in self.process.iter() {
                 if processes.iter().position(|p| p.pid == pid).is_some() {
                     continue;
@@ -59,73 +192,181 @@ impl Opts {
                 }
             }
         } else {
-            if let Some(sysinfo_system) = system.sysinfo_system() {
-                for (&pid, p) in sysinfo_system.processes() {
-                    let pid = pid.as_u32();
-                    match p.status() {
-                        ProcessStatus::Zombie => continue,
-                        _ => {}
-                    }
+            for p in system.list_processes() {
+                if p.status == ProcessStatus::Zombie {
+                    continue;
+                }
 
-                    if let Some(process) = ProcessInfo::new(system, proc_category_len, pid) {
-                        if self.process.contains(&pid) {
-                            processes.push(process);
-                        } else {
-                            for n in self.name.iter() {
-                                if process.name.contains(n) {
-                                    processes.push(process);
-                                    break;
-                                }
-                            }
-                        }
+                if let Some(process) = ProcessInfo::new(system, proc_category_len, p.pid) {
+                    if self.process.contains(&p.pid)
+                        || self.name.iter().any(|n| process.name.contains(n))
+                        || self.cmdline.iter().any(|re| re.is_match(&process.command))
+                        || process.exe.as_ref().is_some_and(|e| exe.contains(e))
+                    {
+                        processes.push(process);
                     }
                 }
             }
         }
 
+        // Explicit pids are dropped above when they have no process info
+        for &pid in self.process.iter() {
+            if processes.iter().any(|p| p.pid == pid) {
+                continue;
+            }
+            match System::process_exists(pid) {
+                Err(Error::AccessDenied) => log::warn!("pid {} access denied, skipping", pid),
+                Ok(true) => log::warn!("pid {} can't be queried, skipping", pid),
+                _ => log::warn!("pid {} not found, skipping", pid),
+            }
+        }
+        processes.retain(|p| !self.excluded(p));
+
         if self.recurse_children {
-            processes.extend(self.recurse_children(
-                system,
-                processes.as_slice(),
-                proc_category_len,
-            ));
+            let mut children =
+                self.recurse_children(system, processes.as_slice(), proc_category_len);
+            children.retain(|p| !self.excluded(p));
+            processes.extend(children);
         }
 
         processes
     }
 
+    // Paths of `--exe` as the system reports them, relative ones are resolved from the working directory
+    fn exe_paths(&self) -> Vec<PathBuf> {
+        self.exe
+            .iter()
+            .map(|p| std::fs::canonicalize(p).unwrap_or_else(|_| p.clone()))
+            .collect()
+    }
+
+    // Whether pids are listed by a source re-read on every sample
+    pub fn lists_pids(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if self.cgroup.is_some() {
+            return true;
+        }
+        #[cfg(target_os = "windows")]
+        if self.job.is_some() {
+            return true;
+        }
+        #[cfg(target_os = "macos")]
+        if self.app.is_some() {
+            return true;
+        }
+        self.pid_file.is_some()
+    }
+
+    // Union of `--pid-file`, `--cgroup`, `--job` and `--app`, None when none of them can be read
+    pub fn listed_pids(&self) -> Option<HashSet<Pid>> {
+        let mut sources: Vec<Option<Vec<Pid>>> = vec![];
+        if let Some(path) = &self.pid_file {
+            sources.push(crate::utils::read_pid_file(path).map(Vec::from_iter));
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(path) = &self.cgroup {
+            sources.push(platform::linux::cgroup_pids(path));
+        }
+        #[cfg(target_os = "windows")]
+        if let Some(name) = &self.job {
+            sources.push(platform::windows::job_pids(name));
+        }
+        #[cfg(target_os = "macos")]
+        if let Some(app) = &self.app {
+            sources.push(platform::macos::app_pids(app));
+        }
+
+        let mut listed = None;
+        for pids in sources.into_iter().flatten() {
+            listed.get_or_insert_with(HashSet::new).extend(pids);
+        }
+        listed
+    }
+
+    pub fn excluded(&self, process: &ProcessInfo) -> bool {
+        self.exclude
+            .iter()
+            .any(|e| process.name.contains(e) || process.command.contains(e))
+    }
+
+    // Processes matching `--name` that aren't recorded yet
+    pub fn follow_processes(
+        &self,
+        system: &System,
+        processes: &[ProcessInfo],
+        proc_category_len: usize,
+    ) -> Vec<ProcessInfo> {
+        system
+            .list_processes()
+            .into_iter()
+            .filter(|p| p.status != ProcessStatus::Zombie)
+            .map(|p| p.pid)
+            .filter(|&pid| processes.iter().all(|p| p.pid != pid))
+            .filter_map(|pid| ProcessInfo::new(system, proc_category_len, pid))
+            .filter(|process| self.name.iter().any(|n| process.name.contains(n)))
+            .filter(|process| !self.excluded(process))
+            .collect()
+    }
+
+    fn top_processes(
+        &self,
+        system: &System,
+        top: usize,
+        proc_category_len: usize,
+    ) -> Vec<ProcessInfo> {
+        let Some(sysinfo_system) = system.sysinfo_system() else {
+            return vec![];
+        };
+
+        let self_pid = std::process::id();
+        let mut candidates: Vec<_> = sysinfo_system
+            .processes()
+            .iter()
+            .filter(|(&pid, p)| {
+                pid.as_u32() != self_pid && p.status() != sysinfo::ProcessStatus::Zombie
+            })
+            .map(|(&pid, p)| (pid.as_u32(), p.cpu_usage()))
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        candidates
+            .into_iter()
+            .filter_map(|(pid, _)| ProcessInfo::new(system, proc_category_len, pid))
+            .take(top)
+            .collect()
+    }
+
     fn recurse_children(
         &self,
         system: &System,
         processes: &[ProcessInfo],
         proc_category_len: usize,
     ) -> Vec<ProcessInfo> {
+        let candidates = system.list_processes();
+        let parents: HashMap<Pid, Option<Pid>> =
+            candidates.iter().map(|p| (p.pid, p.parent)).collect();
+
         let recurse_parent = |mut parent: Pid| {
             if processes.iter().position(|p| p.pid == parent).is_some() {
                 return true;
             }
 
-            if let Some(sysinfo_system) = system.sysinfo_system() {
-                let mut visited = HashSet::new();
-                visited.insert(parent);
-
-                while let Some(parent_process) =
-                    sysinfo_system.process(sysinfo::Pid::from_u32(parent))
-                {
-                    if let Some(parent2) = parent_process.parent() {
-                        let parent2 = parent2.as_u32();
-                        if visited.contains(&parent2) {
-                            return false;
-                        }
+            let mut visited = HashSet::new();
+            visited.insert(parent);
 
-                        if processes.iter().position(|p| p.pid == parent2).is_some() {
-                            return true;
-                        }
-                        visited.insert(parent2);
-                        parent = parent2;
-                    } else {
+            while let Some(&parent_process) = parents.get(&parent) {
+                if let Some(parent2) = parent_process {
+                    if visited.contains(&parent2) {
                         return false;
                     }
+
+                    if processes.iter().position(|p| p.pid == parent2).is_some() {
+                        return true;
+                    }
+                    visited.insert(parent2);
+                    parent = parent2;
+                } else {
+                    return false;
                 }
             }
             false
@@ -133,30 +374,26 @@ impl Opts {
 
         let mut children = vec![];
 
-        if let Some(sysinfo_system) = system.sysinfo_system() {
-            for (&pid, child) in sysinfo_system.processes() {
-                let pid = pid.as_u32();
-                match child.status() {
-                    ProcessStatus::Zombie => continue,
-                    _ => {}
-                }
+        for child in &candidates {
+            let pid = child.pid;
+            if child.status == ProcessStatus::Zombie {
+                continue;
+            }
 
-                if processes.iter().position(|p| p.pid == pid).is_some() {
-                    continue;
-                }
+            if processes.iter().position(|p| p.pid == pid).is_some() {
+                continue;
+            }
 
-                if let Some(parent) = child.parent() {
-                    let parent = parent.as_u32();
-                    if recurse_parent(parent) {
+            if let Some(parent) = child.parent {
+                if recurse_parent(parent) {
+                    if let Some(p) = ProcessInfo::new(system, proc_category_len, pid) {
+                        children.push(p);
+                    }
+                } else if let Some(rpid) = system.process_responsible(pid) {
+                    if processes.iter().position(|p| p.pid == rpid).is_some() {
                         if let Some(p) = ProcessInfo::new(system, proc_category_len, pid) {
                             children.push(p);
                         }
-                    } else if let Some(rpid) = system.process_responsible(pid) {
-                        if processes.iter().position(|p| p.pid == rpid).is_some() {
-                            if let Some(p) = ProcessInfo::new(system, proc_category_len, pid) {
-                                children.push(p);
-                            }
-                        }
                     }
                 }
             }
@@ -166,9 +403,121 @@ impl Opts {
     }
 }
 
+// Sizes are sampled in M and KBps, or as bytes with `--raw-units`
+fn output_unit(unit: &'static str, raw_units: bool) -> (&'static str, f32) {
+    match unit {
+        "M" if raw_units => ("B", 1024. * 1024.),
+        "KBps" if raw_units => ("Bps", 1024.),
+        _ => (unit, 1.),
+    }
+}
+
+fn parse_y_max(s: &str) -> Result<(Category, f32), String> {
+    let (category, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <category>=<value>, got `{}`", s))?;
+    let category = Category::from_str(category, false)?;
+    let value: f32 = value.parse().map_err(|err| format!("{}: {}", value, err))?;
+    if value <= 0. {
+        return Err(format!("{} should be positive", value));
+    }
+    Ok((category, value))
+}
+
+// Options of `--config`, keys are named after the long flags
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigFile {
+    process: Option<Vec<Pid>>,
+    name: Option<Vec<String>>,
+    cmdline: Option<Vec<String>>,
+    exe: Option<Vec<PathBuf>>,
+    exclude: Option<Vec<String>>,
+    output: Option<Vec<PathBuf>>,
+    interval: Option<u64>,
+    count: Option<usize>,
+    time: Option<String>,
+    category: Option<Vec<String>>,
+    gpu_calc: Option<String>,
+}
+
+impl ConfigFile {
+    // Fill `opts` with values of the file unless they are given on the command line
+    pub fn merge(self, opts: &mut Opts, matches: &ArgMatches) -> Result<(), String> {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let Some(process) = self.process.filter(|_| !from_cli("process")) {
+            opts.process = process;
+        }
+        if let Some(name) = self.name.filter(|_| !from_cli("name")) {
+            opts.name = name;
+        }
+        if let Some(cmdline) = self.cmdline.filter(|_| !from_cli("cmdline")) {
+            opts.cmdline = cmdline
+                .iter()
+                .map(|re| Regex::new(re).map_err(|err| format!("cmdline: {}", err)))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(exe) = self.exe.filter(|_| !from_cli("exe")) {
+            opts.exe = exe;
+        }
+        if let Some(exclude) = self.exclude.filter(|_| !from_cli("exclude")) {
+            opts.exclude = exclude;
+        }
+        if let Some(output) = self.output.filter(|_| !from_cli("output")) {
+            opts.output = output;
+        }
+        if let Some(interval) = self.interval.filter(|_| !from_cli("interval")) {
+            opts.interval = interval;
+        }
+        if let Some(count) = self.count.filter(|_| !from_cli("count")) {
+            opts.count = Some(count);
+        }
+        if let Some(time) = self.time.filter(|_| !from_cli("time")) {
+            opts.time = Some(time.parse().map_err(|err| format!("time: {}", err))?);
+        }
+        if let Some(category) = self.category.filter(|_| !from_cli("category")) {
+            opts.category = category
+                .iter()
+                .map(|c| Category::from_str(c, false).map_err(|err| format!("category: {}", err)))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(gpu_calc) = self.gpu_calc.filter(|_| !from_cli("gpu_calc")) {
+            opts.gpu_calc = GpuCalculation::from_str(&gpu_calc, false)
+                .map_err(|err| format!("gpu-calc: {}", err))?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Action {
-    ThreadList { pid: Pid },
+    ThreadList {
+        pid: Pid,
+    },
+    /// Overlay `.json` captures in one chart per category, e.g., before/after a change
+    Merge {
+        #[arg(required = true, num_args = 2..)]
+        input: Vec<PathBuf>,
+        /// `.svg` or `.html` outputs
+        #[arg(short, long, required = true, num_args = 1..)]
+        output: Vec<PathBuf>,
+    },
+    /// Idle captures for `--baseline`
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BaselineAction {
+    /// Record as usual into a single `.json`, options are given before `baseline`
+    Capture {
+        #[arg(short, long)]
+        output: PathBuf,
+    },
 }
 
 impl Action {
@@ -196,6 +545,10 @@ impl Action {
                     }
                 }
             }
+            // Handled by `merge::merge`, it doesn't sample anything
+            Self::Merge { .. } => {}
+            // Turned into a normal recording in `main`
+            Self::Baseline { .. } => {}
         }
 
         Ok(())
@@ -205,41 +558,73 @@ impl Action {
 #[derive(ValueEnum, Debug, Copy, Clone)]
 #[clap(rename_all = "snake_case")]
 pub enum Category {
-    CPU,
+    Cpu,
     Mem,
     Alloc,
-    GPU,
+    Gpu,
     Vram,
-    FPS,
+    VramShared,
+    Fps,
     NetIn,
     NetOut,
     DiskRead,
     DiskWrite,
+    DiskReadOps,
+    DiskWriteOps,
     Kobject,
+    MachPorts,
+    Fds,
+    Sockets,
+    #[value(alias = "threads")]
     Thread,
+    PageFaults,
+    CtxSwitches,
+    IoWait,
+    Power,
+    Uptime,
     SysCpu,
+    SysCpuTotal,
     SysCPUFreq,
     SysCPUTemp,
     SysGPU,
+    SysGpuDevices,
     SysPower,
+    SysBatteryPct,
     SysNpuPower,
+    SysMem,
+    SysSwap,
+    SysNetIn,
+    SysNetOut,
+    SysDiskRead,
+    SysDiskWrite,
 }
 
 impl Category {
     pub fn to_process(self) -> Option<ProcessCategory> {
         match self {
-            Category::CPU => Some(ProcessCategory::Cpu),
+            Category::Cpu => Some(ProcessCategory::Cpu),
             Category::Mem => Some(ProcessCategory::Mem),
             Category::Alloc => Some(ProcessCategory::Alloc),
-            Category::GPU => Some(ProcessCategory::Gpu),
+            Category::Gpu => Some(ProcessCategory::Gpu),
             Category::Vram => Some(ProcessCategory::Vram),
-            Category::FPS => Some(ProcessCategory::Fps),
+            Category::VramShared => Some(ProcessCategory::VramShared),
+            Category::Fps => Some(ProcessCategory::Fps),
             Category::NetIn => Some(ProcessCategory::NetIn),
             Category::NetOut => Some(ProcessCategory::NetOut),
             Category::DiskRead => Some(ProcessCategory::DiskRead),
             Category::DiskWrite => Some(ProcessCategory::DiskWrite),
+            Category::DiskReadOps => Some(ProcessCategory::DiskReadOps),
+            Category::DiskWriteOps => Some(ProcessCategory::DiskWriteOps),
             Category::Kobject => Some(ProcessCategory::Kobject),
+            Category::MachPorts => Some(ProcessCategory::MachPorts),
+            Category::Fds => Some(ProcessCategory::Fds),
+            Category::Sockets => Some(ProcessCategory::Sockets),
             Category::Thread => Some(ProcessCategory::Thread),
+            Category::PageFaults => Some(ProcessCategory::PageFaults),
+            Category::CtxSwitches => Some(ProcessCategory::CtxSwitches),
+            Category::IoWait => Some(ProcessCategory::IoWait),
+            Category::Power => Some(ProcessCategory::Power),
+            Category::Uptime => Some(ProcessCategory::Uptime),
             _ => None,
         }
     }
@@ -247,17 +632,26 @@ impl Category {
     pub fn to_system(self) -> Option<SystemCategory> {
         match self {
             Category::SysCpu => Some(SystemCategory::Cpu),
+            Category::SysCpuTotal => Some(SystemCategory::CpuTotal),
             Category::SysCPUFreq => Some(SystemCategory::CpuFreq),
             Category::SysCPUTemp => Some(SystemCategory::CpuTemp),
             Category::SysGPU => Some(SystemCategory::Gpu),
+            Category::SysGpuDevices => Some(SystemCategory::GpuDevices),
             Category::SysPower => Some(SystemCategory::Power),
+            Category::SysBatteryPct => Some(SystemCategory::BatteryPct),
             Category::SysNpuPower => Some(SystemCategory::NpuPower),
+            Category::SysMem => Some(SystemCategory::Mem),
+            Category::SysSwap => Some(SystemCategory::Swap),
+            Category::SysNetIn => Some(SystemCategory::NetIn),
+            Category::SysNetOut => Some(SystemCategory::NetOut),
+            Category::SysDiskRead => Some(SystemCategory::DiskRead),
+            Category::SysDiskWrite => Some(SystemCategory::DiskWrite),
             _ => None,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum ProcessCategory {
     Cpu,
@@ -265,13 +659,24 @@ pub enum ProcessCategory {
     Alloc,
     Gpu,
     Vram,
+    VramShared,
     Fps,
     NetIn,
     NetOut,
     DiskRead,
     DiskWrite,
+    DiskReadOps,
+    DiskWriteOps,
     Kobject,
+    MachPorts,
+    Fds,
+    Sockets,
     Thread,
+    PageFaults,
+    CtxSwitches,
+    IoWait,
+    Power,
+    Uptime,
 }
 
 impl ProcessCategory {
@@ -282,13 +687,76 @@ impl ProcessCategory {
             Self::Alloc => "M",
             Self::Gpu => "%",
             Self::Vram => "M",
+            Self::VramShared => "M",
             Self::Fps => "",
             Self::NetIn => "KBps",
             Self::NetOut => "KBps",
             Self::DiskRead => "KBps",
             Self::DiskWrite => "KBps",
+            Self::DiskReadOps => "/s",
+            Self::DiskWriteOps => "/s",
             Self::Kobject => "",
+            Self::MachPorts => "",
+            Self::Fds => "",
+            Self::Sockets => "",
             Self::Thread => "",
+            Self::PageFaults => "/s",
+            Self::CtxSwitches => "/s",
+            Self::IoWait => "%",
+            Self::Power => "mW",
+            Self::Uptime => "s",
+        }
+    }
+
+    // Values are per second, their integral over the recording is a total
+    pub fn is_rate(&self) -> bool {
+        matches!(
+            self,
+            Self::NetIn
+                | Self::NetOut
+                | Self::DiskRead
+                | Self::DiskWrite
+                | Self::DiskReadOps
+                | Self::DiskWriteOps
+                | Self::PageFaults
+                | Self::CtxSwitches
+        )
+    }
+
+    // Integral of a rate category, e.g., 1.2 GB for traffic in KBps
+    pub fn format_total(&self, total: f32) -> String {
+        if self.unit() != "KBps" {
+            return format!("{:.0}", total + 0.);
+        }
+
+        // Adding 0 turns -0 into 0, which prints without a sign
+        let mut total = total + 0.;
+        let mut unit = "KB";
+        for next in ["MB", "GB", "TB"] {
+            if total < 1024. {
+                break;
+            }
+            total /= 1024.;
+            unit = next;
+        }
+        format!("{:.1} {}", total, unit)
+    }
+
+    // Unit of sampled values, i.e., of csv/json outputs, and how many of it make one `unit()`
+    pub fn output_unit(&self, raw_units: bool) -> (&'static str, f32) {
+        output_unit(self.unit(), raw_units)
+    }
+
+    // Decimal places unless `--precision` is given, counts are whole numbers
+    pub fn precision(&self) -> usize {
+        match self {
+            Self::Kobject
+            | Self::MachPorts
+            | Self::Fds
+            | Self::Sockets
+            | Self::Thread
+            | Self::Uptime => 0,
+            _ => 2,
         }
     }
 
@@ -299,110 +767,461 @@ impl ProcessCategory {
             Self::Alloc => Color::AnsiValue(125),
             Self::Gpu => Color::AnsiValue(208),
             Self::Vram => Color::AnsiValue(64),
+            Self::VramShared => Color::AnsiValue(106),
             Self::Fps => Color::DarkYellow,
             Self::NetIn => Color::DarkBlue,
             Self::NetOut => Color::DarkMagenta,
             Self::DiskRead => Color::AnsiValue(143),
             Self::DiskWrite => Color::AnsiValue(136),
+            Self::DiskReadOps => Color::AnsiValue(108),
+            Self::DiskWriteOps => Color::AnsiValue(180),
             Self::Kobject => Color::AnsiValue(215),
+            Self::MachPorts => Color::AnsiValue(209),
+            Self::Fds => Color::AnsiValue(221),
+            Self::Sockets => Color::AnsiValue(173),
             Self::Thread => Color::AnsiValue(84),
+            Self::PageFaults => Color::AnsiValue(167),
+            Self::CtxSwitches => Color::AnsiValue(110),
+            Self::IoWait => Color::AnsiValue(131),
+            Self::Power => Color::AnsiValue(78),
+            Self::Uptime => Color::AnsiValue(245),
         }
     }
 
     pub fn lower_bound(&self) -> f32 {
         match self {
+            // A busy core with `--cpu-scale per-core`, the whole machine with `normalized`
             Self::Cpu => 100.,
             Self::Mem => 10.,
             Self::Alloc => 10.,
             Self::Gpu => 100.,
             Self::Vram => 10.,
+            Self::VramShared => 10.,
             Self::Fps => 60.,
             Self::NetIn => (1 << 10) as _,
             Self::NetOut => (1 << 10) as _,
             Self::DiskRead => (1 << 10) as _,
             Self::DiskWrite => (1 << 10) as _,
+            Self::DiskReadOps => 100.,
+            Self::DiskWriteOps => 100.,
             Self::Kobject => 100.,
-            Self::Thread => 50.,
+            Self::MachPorts => 100.,
+            Self::Fds => 100.,
+            Self::Sockets => 10.,
+            Self::Thread => 100.,
+            Self::PageFaults => 100.,
+            Self::CtxSwitches => 1000.,
+            Self::IoWait => 100.,
+            Self::Power => 1000.,
+            Self::Uptime => 60.,
+        }
+    }
+
+    // Whether the current platform provides the category, see Supported Platforms of README
+    pub fn supported(&self) -> bool {
+        match self {
+            Self::Alloc | Self::VramShared => cfg!(target_os = "windows"),
+            Self::Vram | Self::NetIn | Self::NetOut => !cfg!(target_os = "linux"),
+            Self::DiskReadOps | Self::DiskWriteOps | Self::PageFaults | Self::CtxSwitches => {
+                !cfg!(target_os = "macos")
+            }
+            Self::IoWait => cfg!(target_os = "linux"),
+            Self::MachPorts => cfg!(target_os = "macos"),
+            Self::Fds => !cfg!(target_os = "windows"),
+            Self::Power => cfg!(target_os = "macos"),
+            _ => true,
+        }
+    }
+
+    // The category of `System::sample_process` this one is read from
+    fn core(&self, gpu_calc: GpuCalculation) -> precord_core::ProcessCategory {
+        use precord_core::ProcessCategory as Core;
+        match self {
+            Self::Cpu => Core::Cpu,
+            Self::Mem => Core::Mem,
+            Self::Alloc => Core::Alloc,
+            Self::Gpu => Core::Gpu(gpu_calc.into()),
+            Self::Vram => Core::Vram(gpu_calc.into()),
+            Self::VramShared => Core::VramShared(gpu_calc.into()),
+            Self::Fps => Core::Fps,
+            Self::NetIn => Core::NetIn,
+            Self::NetOut => Core::NetOut,
+            Self::DiskRead => Core::DiskRead,
+            Self::DiskWrite => Core::DiskWrite,
+            Self::DiskReadOps => Core::DiskReadOps,
+            Self::DiskWriteOps => Core::DiskWriteOps,
+            Self::Kobject => Core::Kobject,
+            Self::MachPorts => Core::MachPorts,
+            Self::Fds => Core::Fds,
+            Self::Sockets => Core::Sockets,
+            Self::Thread => Core::Thread,
+            Self::PageFaults => Core::PageFaults,
+            Self::CtxSwitches => Core::CtxSwitches,
+            Self::IoWait => Core::IoWait,
+            Self::Power => Core::Power,
+            Self::Uptime => Core::Uptime,
         }
     }
 
-    pub fn sample(&self, system: &mut System, gpu_calc: GpuCalculation, pid: Pid) -> Option<f32> {
+    // Sizes in M and KBps, or bytes with `raw_units`
+    pub fn sample(
+        &self,
+        system: &mut System,
+        gpu_calc: GpuCalculation,
+        pid: Pid,
+        raw_units: bool,
+    ) -> Option<f32> {
+        let s = system.sample_process(pid, &[self.core(gpu_calc)]);
         match self {
-            Self::Cpu => system.process_cpu_usage(pid),
-            Self::Mem => system.process_mem(pid).map(|v| (v >> 10) as f32 / 1024.),
-            Self::Alloc => system.process_alloc(pid).map(|v| (v >> 10) as f32 / 1024.),
-            Self::Gpu => system.process_gpu_usage(pid, gpu_calc.into()),
-            Self::Vram => system
-                .process_vram(pid, gpu_calc.into())
-                .map(|v| v / (1 << 20) as f32),
-            Self::Fps => Some(system.process_fps(pid)),
-            Self::NetIn => system.process_net_traffic_in(pid).map(|v| (v >> 10) as f32),
-            Self::NetOut => system
-                .process_net_traffic_out(pid)
-                .map(|v| (v >> 10) as f32),
-            Self::DiskRead => system.process_disk_read(pid).map(|v| v / 1024.),
-            Self::DiskWrite => system.process_disk_write(pid).map(|v| v / 1024.),
-            Self::Kobject => system.process_kobject(pid).map(|v| v as _),
-            Self::Thread => system.process_threads(pid).map(|v| v as _),
+            Self::Cpu => s.cpu_usage,
+            Self::Mem if raw_units => s.mem.map(|v| v as f32),
+            Self::Mem => s.mem.map(|v| (v >> 10) as f32 / 1024.),
+            Self::Alloc if raw_units => s.alloc.map(|v| v as f32),
+            Self::Alloc => s.alloc.map(|v| (v >> 10) as f32 / 1024.),
+            Self::Gpu => s.gpu_usage,
+            Self::Vram if raw_units => s.vram,
+            Self::Vram => s.vram.map(|v| v / (1 << 20) as f32),
+            Self::VramShared if raw_units => s.vram_shared,
+            Self::VramShared => s.vram_shared.map(|v| v / (1 << 20) as f32),
+            Self::Fps => s.fps,
+            Self::NetIn if raw_units => s.net_traffic_in.map(|v| v as f32),
+            Self::NetIn => s.net_traffic_in.map(|v| (v >> 10) as f32),
+            Self::NetOut if raw_units => s.net_traffic_out.map(|v| v as f32),
+            Self::NetOut => s.net_traffic_out.map(|v| (v >> 10) as f32),
+            Self::DiskRead if raw_units => s.disk_read,
+            Self::DiskRead => s.disk_read.map(|v| v / 1024.),
+            Self::DiskWrite if raw_units => s.disk_write,
+            Self::DiskWrite => s.disk_write.map(|v| v / 1024.),
+            Self::DiskReadOps => s.disk_read_ops,
+            Self::DiskWriteOps => s.disk_write_ops,
+            Self::Kobject => s.kobject.map(|v| v as _),
+            Self::MachPorts => s.mach_ports.map(|v| v as _),
+            Self::Fds => s.fds.map(|v| v as _),
+            Self::Sockets => s.sockets.map(|v| v as _),
+            Self::Thread => s.threads.map(|v| v as _),
+            Self::PageFaults => s.page_faults,
+            Self::CtxSwitches => s.ctx_switches,
+            Self::IoWait => s.io_wait,
+            Self::Power => s.power,
+            Self::Uptime => s.uptime.map(|v| v.as_secs_f32()),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum SystemCategory {
     Cpu,
+    CpuTotal,
     CpuFreq,
     CpuTemp,
     Gpu,
+    GpuDevices,
     Power,
+    BatteryPct,
     NpuPower,
+    Mem,
+    Swap,
+    NetIn,
+    NetOut,
+    DiskRead,
+    DiskWrite,
 }
 
 impl SystemCategory {
     pub fn unit(&self) -> &'static str {
         match self {
             Self::Cpu => "%",
+            Self::CpuTotal => "%",
             Self::CpuFreq => "MHz",
             Self::CpuTemp => "°C",
             Self::Gpu => "%",
+            Self::GpuDevices => "%",
             Self::Power => "W",
+            Self::BatteryPct => "%",
             Self::NpuPower => "W",
+            Self::Mem => "M",
+            Self::Swap => "M",
+            Self::NetIn => "KBps",
+            Self::NetOut => "KBps",
+            Self::DiskRead => "KBps",
+            Self::DiskWrite => "KBps",
+        }
+    }
+
+    // Unit of sampled values, i.e., of csv/json outputs, and how many of it make one `unit()`
+    pub fn output_unit(&self, raw_units: bool) -> (&'static str, f32) {
+        output_unit(self.unit(), raw_units)
+    }
+
+    // Decimal places unless `--precision` is given
+    pub fn precision(&self) -> usize {
+        match self {
+            Self::CpuFreq => 0,
+            _ => 2,
         }
     }
 
     pub fn color(&self) -> Color {
         match self {
             Self::Cpu => Color::DarkGreen,
+            Self::CpuTotal => Color::Green,
             Self::CpuFreq => Color::DarkCyan,
             Self::CpuTemp => Color::AnsiValue(208),
             Self::Gpu => Color::AnsiValue(64),
+            Self::GpuDevices => Color::AnsiValue(64),
             Self::Power => Color::AnsiValue(78),
+            Self::BatteryPct => Color::AnsiValue(112),
             Self::NpuPower => Color::AnsiValue(160),
+            Self::Mem => Color::DarkCyan,
+            Self::Swap => Color::AnsiValue(125),
+            Self::NetIn => Color::DarkBlue,
+            Self::NetOut => Color::AnsiValue(33),
+            Self::DiskRead => Color::AnsiValue(94),
+            Self::DiskWrite => Color::AnsiValue(130),
         }
     }
 
     pub fn lower_bound(&self) -> f32 {
         match self {
             Self::Cpu => 100.,
+            Self::CpuTotal => 100.,
             Self::CpuFreq => 1000.,
             Self::CpuTemp => 100.,
             Self::Gpu => 100.,
+            Self::GpuDevices => 100.,
             Self::Power => 50.,
+            Self::BatteryPct => 100.,
             Self::NpuPower => 10.,
+            Self::Mem => 1024.,
+            Self::Swap => 1024.,
+            Self::NetIn => 1024.,
+            Self::NetOut => 1024.,
+            Self::DiskRead => 1024.,
+            Self::DiskWrite => 1024.,
+        }
+    }
+
+    // Names of the rows returned by `sample`, empty when they are just indexed
+    pub fn row_labels(
+        &self,
+        system: &mut System,
+        gpu_calc: GpuCalculation,
+        gpu_engines: bool,
+    ) -> Vec<String> {
+        match self {
+            Self::Gpu if gpu_engines => {
+                let adapters = system
+                    .system_gpu_usage(gpu_calc.into())
+                    .unwrap_or_default()
+                    .len();
+                (0..adapters)
+                    .map(|i| format!("{:?}{}", self, i))
+                    .chain(
+                        system
+                            .system_gpu_engines(gpu_calc.into())
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|(ty, _)| ty),
+                    )
+                    .collect()
+            }
+            Self::GpuDevices => system
+                .system_gpu_devices()
+                .map(|devices| {
+                    devices
+                        .iter()
+                        .map(|d| {
+                            let kind = if d.integrated {
+                                "Integrated"
+                            } else {
+                                "Discrete"
+                            };
+                            format!("{} ({})", d.name, kind)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Self::CpuFreq => system
+                .system_cpu_frequency()
+                .map(|cpus| {
+                    cpus.into_iter()
+                        .enumerate()
+                        .map(|(i, f)| match f.group {
+                            Some(group) => format!("{} CPU{}", group, i),
+                            None => format!("{:?}{}", self, i),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Self::DiskRead | Self::DiskWrite => system
+                .system_disk_io()
+                .map(|disks| disks.into_iter().map(|d| d.name).collect())
+                .unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+
+    // Probes the source of the category once, false when the platform or machine lacks it
+    pub fn supported(&self, system: &mut System, gpu_calc: GpuCalculation) -> bool {
+        fn available<T>(r: Result<T, Error>) -> bool {
+            !matches!(r, Err(Error::UnsupportedFeatures(_)))
+        }
+
+        match self {
+            Self::Cpu | Self::CpuTotal | Self::Mem | Self::Swap | Self::NetIn | Self::NetOut => {
+                true
+            }
+            Self::CpuFreq => available(system.system_cpu_frequency()),
+            Self::CpuTemp => available(system.system_cpu_temperature()),
+            Self::Gpu => system.system_gpu_usage(gpu_calc.into()).is_some(),
+            Self::GpuDevices => available(system.system_gpu_devices()),
+            Self::Power => available(system.system_power()),
+            Self::BatteryPct => available(system.system_battery_percent()),
+            Self::NpuPower => available(system.system_npu_power()),
+            Self::DiskRead | Self::DiskWrite => available(system.system_disk_io()),
         }
     }
 
-    pub fn sample(&self, system: &mut System, gpu_calc: GpuCalculation) -> Vec<f32> {
+    // Rows of every engine type follow the adapters of `Gpu` with `gpu_engines`
+    pub fn sample(
+        &self,
+        system: &mut System,
+        gpu_calc: GpuCalculation,
+        gpu_engines: bool,
+        raw_units: bool,
+    ) -> Vec<f32> {
+        let bytes = |v: u64| {
+            if raw_units {
+                v as f32
+            } else {
+                (v >> 10) as f32 / 1024.
+            }
+        };
+        let kib = if raw_units { 1. } else { 1024. };
         match self {
             Self::Cpu => system.system_cpu_usage().unwrap_or_default(),
-            Self::CpuFreq => system.system_cpu_frequency().unwrap_or_default(),
+            // A single row over all cpus
+            Self::CpuTotal => vec![system.system_cpu_total_usage().unwrap_or(0.)],
+            Self::CpuFreq => system
+                .system_cpu_frequency()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|f| f.frequency)
+                .collect(),
             Self::CpuTemp => system.system_cpu_temperature().unwrap_or_default(),
             Self::Gpu => {
-                vec![system.system_gpu_usage(gpu_calc.into()).unwrap_or(0.0)]
+                let mut rows = system.system_gpu_usage(gpu_calc.into()).unwrap_or_default();
+                if gpu_engines {
+                    rows.extend(
+                        system
+                            .system_gpu_engines(gpu_calc.into())
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|(_, v)| v),
+                    );
+                }
+                rows
             }
+            Self::GpuDevices => system
+                .system_gpu_devices()
+                .map(|devices| devices.iter().map(|d| d.usage).collect())
+                .unwrap_or_default(),
             Self::Power => vec![system.system_power().unwrap_or(0.)],
+            Self::BatteryPct => vec![system.system_battery_percent().unwrap_or(0.)],
             Self::NpuPower => vec![system.system_npu_power().unwrap_or(0.)],
+            Self::Mem => vec![system.system_memory_used().map(bytes).unwrap_or(0.)],
+            Self::Swap => vec![system.system_swap_used().map(bytes).unwrap_or(0.)],
+            Self::NetIn => vec![system
+                .system_net_traffic()
+                .map(|(v, _)| v / kib)
+                .unwrap_or(0.)],
+            Self::NetOut => vec![system
+                .system_net_traffic()
+                .map(|(_, v)| v / kib)
+                .unwrap_or(0.)],
+            // A row per disk
+            Self::DiskRead => system
+                .system_disk_io()
+                .map(|disks| disks.iter().map(|d| d.read / kib).collect())
+                .unwrap_or_default(),
+            Self::DiskWrite => system
+                .system_disk_io()
+                .map(|disks| disks.iter().map(|d| d.write / kib).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Progress {
+    // Nothing per sample
+    Off,
+    // Only the `i / count` line
+    Summary,
+    // Readings of every process and system category too
+    Full,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone, Default)]
+pub enum TimestampFormat {
+    // Local time with offset, e.g., 2024-01-02T15:04:05.123+08:00
+    #[default]
+    Rfc3339,
+    // e.g., 2024-01-02T07:04:05.123Z
+    Rfc3339Utc,
+    // Integer milliseconds since the Unix epoch
+    EpochMs,
+}
+
+impl TimestampFormat {
+    pub fn format(&self, t: &chrono::DateTime<chrono::Local>) -> Timestamp {
+        match self {
+            Self::Rfc3339 => Timestamp::Text(t.to_rfc3339()),
+            Self::Rfc3339Utc => Timestamp::Text(
+                t.with_timezone(&chrono::Utc)
+                    .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+            ),
+            Self::EpochMs => Timestamp::Millis(t.timestamp_millis()),
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SmoothMode {
+    // The moving average over a faded raw line
+    Both,
+    // Only the moving average
+    Smoothed,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone, Default)]
+pub enum CsvLayout {
+    // A block per category with a column per process or row
+    #[default]
+    Wide,
+    // A row per value: timestamp,kind,category,pid,name,row_index,value
+    Long,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+pub enum CpuScale {
+    PerCore,
+    Normalized,
+}
+
+impl From<CpuScale> for precord_core::CpuScale {
+    fn from(scale: CpuScale) -> Self {
+        match scale {
+            CpuScale::PerCore => Self::PerCore,
+            CpuScale::Normalized => Self::Normalized,
         }
     }
 }
@@ -411,6 +1230,7 @@ impl SystemCategory {
 pub enum GpuCalculation {
     Max,
     Sum,
+    Average,
 }
 
 impl From<GpuCalculation> for precord_core::GpuCalculation {
@@ -418,6 +1238,7 @@ impl From<GpuCalculation> for precord_core::GpuCalculation {
         match calc {
             GpuCalculation::Max => Self::Max,
             GpuCalculation::Sum => Self::Sum,
+            GpuCalculation::Average => Self::Average,
         }
     }
 }
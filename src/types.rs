@@ -1,12 +1,22 @@
+use crate::opt::{Category, ProcessCategory, SmoothMode, SystemCategory};
 use crate::Pid;
 use precord_core::System;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
+#[derive(Clone)]
 pub struct ProcessInfo {
     pub pid: Pid,
     pub name: String,
     pub command: String,
+    pub exe: Option<PathBuf>,
+    // Seconds since the Unix epoch
+    pub start_time: Option<u64>,
     pub values: Vec<Vec<f32>>,
     pub valid: bool,
+    // Index of the first sample after the process exited, values from it are zeros
+    pub ended: Option<usize>,
 }
 
 impl ProcessInfo {
@@ -18,26 +28,306 @@ impl ProcessInfo {
             pid,
             name,
             command,
+            exe: system.process_exe(pid),
+            start_time: system.process_start_time(pid),
             values: vec![vec![]; proc_category_len],
             valid: true,
+            ended: None,
         })
     }
 
+    // Values recorded while the process was alive
+    pub fn live_values(&self, idx: usize) -> &[f32] {
+        let values = &self.values[idx];
+        &values[..self.ended.unwrap_or(values.len()).min(values.len())]
+    }
+
     pub fn avg_value(&self, idx: usize) -> f32 {
-        if self.values[idx].is_empty() {
+        let values = self.live_values(idx);
+        if values.is_empty() {
             0.0
         } else {
-            self.values[idx].iter().sum::<f32>() / (self.values[idx].len() as f32)
+            values.iter().sum::<f32>() / (values.len() as f32)
+        }
+    }
+
+    pub fn min_value(&self, idx: usize) -> f32 {
+        self.percentile(idx, 0.)
+    }
+
+    pub fn max_value(&self, idx: usize) -> f32 {
+        self.percentile(idx, 100.)
+    }
+
+    pub fn percentile(&self, idx: usize, p: f32) -> f32 {
+        percentile(self.live_values(idx), p).unwrap_or(0.0)
+    }
+
+    pub fn smoothed_values(&self, idx: usize, window: usize) -> Vec<f32> {
+        moving_average(self.live_values(idx), window)
+    }
+
+    // Integral of a per-second category, None without the durations of samples
+    pub fn total_value(&self, idx: usize, durations: &[f32]) -> Option<f32> {
+        integral(self.live_values(idx), durations)
+    }
+}
+
+// `--smooth` of chart outputs
+#[derive(Debug, Copy, Clone)]
+pub struct Smoothing {
+    pub window: usize,
+    pub mode: SmoothMode,
+}
+
+impl Smoothing {
+    pub fn new(window: Option<u64>, mode: SmoothMode) -> Option<Self> {
+        Some(Self {
+            window: window? as usize,
+            mode,
+        })
+    }
+
+    // The raw line is drawn faded under the smoothed one
+    pub fn keeps_raw(&self) -> bool {
+        self.mode == SmoothMode::Both
+    }
+}
+
+// Mean of every value and the ones before it within the window
+pub fn moving_average(values: &[f32], window: usize) -> Vec<f32> {
+    let window = window.max(1);
+    let mut sum = 0.;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| {
+            sum += v;
+            if i >= window {
+                sum -= values[i - window];
+            }
+            sum / (i + 1).min(window) as f32
+        })
+        .collect()
+}
+
+// Keeps both ends of a long string, e.g., the binary and the arguments telling commands apart
+pub fn ellipsize_middle(s: &str, width: usize) -> String {
+    let len = s.chars().count();
+    if len <= width {
+        return s.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let head = (width - 1).div_ceil(2);
+    let tail = width - 1 - head;
+    let mut short: String = s.chars().take(head).collect();
+    short.push('…');
+    short.extend(s.chars().skip(len - tail));
+    short
+}
+
+// Sum of every value times the time its sample covered
+pub fn integral(values: &[f32], durations: &[f32]) -> Option<f32> {
+    if durations.is_empty() {
+        return None;
+    }
+    Some(values.iter().zip(durations).map(|(v, d)| v * d).sum())
+}
+
+// Nearest-rank percentile, `p` in 0..=100
+pub fn percentile(values: &[f32], p: f32) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let rank = (p / 100. * sorted.len() as f32).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+// Fixed tops of chart y-axes from `--y-max`, the later one wins for a category
+#[derive(Default, Clone)]
+pub struct YAxisMax(pub Vec<(Category, f32)>);
+
+impl YAxisMax {
+    pub fn process(&self, c: ProcessCategory) -> Option<f32> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(category, _)| category.to_process() == Some(c))
+            .map(|&(_, v)| v)
+    }
+
+    pub fn system(&self, c: SystemCategory) -> Option<f32> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(category, _)| category.to_system() == Some(c))
+            .map(|&(_, v)| v)
+    }
+}
+
+// Drop the oldest values so at most `n` are left
+pub fn keep_last<T>(values: &mut Vec<T>, n: usize) {
+    if values.len() > n {
+        values.drain(..values.len() - n);
+    }
+}
+
+// Copies with sizes back in M and KBps, for outputs other than csv/json when `--raw-units` samples
+// bytes
+pub fn display_units(
+    proc_categories: &[ProcessCategory],
+    sys_categories: &[SystemCategory],
+    processes: &[ProcessInfo],
+    system_metrics: &[SystemMetrics],
+) -> (Vec<ProcessInfo>, Vec<SystemMetrics>) {
+    let mut processes = processes.to_vec();
+    for p in processes.iter_mut() {
+        for (values, c) in p.values.iter_mut().zip(proc_categories) {
+            let (_, scale) = c.output_unit(true);
+            values.iter_mut().for_each(|v| *v /= scale);
+        }
+    }
+    let mut system_metrics = system_metrics.to_vec();
+    for (metrics, c) in system_metrics.iter_mut().zip(sys_categories) {
+        let (_, scale) = c.output_unit(true);
+        for row in metrics.rows.iter_mut() {
+            row.iter_mut().for_each(|v| *v /= scale);
+        }
+    }
+    (processes, system_metrics)
+}
+
+// Merge processes sharing a name, returns the merged processes and the merged index of each process
+pub fn group_by_name(
+    processes: &[ProcessInfo],
+    proc_category_len: usize,
+) -> (Vec<ProcessInfo>, Vec<usize>) {
+    let mut groups: Vec<ProcessInfo> = vec![];
+    let mut group_index = vec![];
+
+    for p in processes {
+        let gi = match groups.iter().position(|g| g.name == p.name) {
+            Some(gi) => gi,
+            None => {
+                groups.push(ProcessInfo {
+                    pid: p.pid,
+                    name: p.name.clone(),
+                    command: p.command.clone(),
+                    exe: p.exe.clone(),
+                    start_time: p.start_time,
+                    values: vec![vec![]; proc_category_len],
+                    valid: true,
+                    ended: None,
+                });
+                groups.len() - 1
+            }
+        };
+        group_index.push(gi);
+    }
+
+    (groups, group_index)
+}
+
+// Parents of every process, for finding the recorded ancestor of a process
+pub struct Ancestry {
+    parents: HashMap<Pid, Option<Pid>>,
+}
+
+impl Ancestry {
+    pub fn new(system: &System) -> Self {
+        Self {
+            parents: system
+                .list_processes()
+                .into_iter()
+                .map(|p| (p.pid, p.parent))
+                .collect(),
+        }
+    }
+
+    // The topmost ancestor of `pid` in `pids`, the responsible process counts as a parent, `pid`
+    // itself when there is none
+    pub fn root(&self, system: &System, pids: &HashSet<Pid>, pid: Pid) -> Pid {
+        let mut root = pid;
+        let mut visited = HashSet::new();
+        let mut current = pid;
+
+        while visited.insert(current) {
+            let parent = self.parents.get(&current).copied().flatten().or_else(|| {
+                system
+                    .process_responsible(current)
+                    .filter(|&rpid| rpid != current)
+            });
+            let Some(parent) = parent else {
+                break;
+            };
+            if pids.contains(&parent) {
+                root = parent;
+            }
+            current = parent;
         }
+
+        root
+    }
+}
+
+// Merge processes into their topmost recorded ancestor, returns the roots and the root index of
+// each process
+pub fn rollup_to_parent(
+    system: &System,
+    processes: &[ProcessInfo],
+    proc_category_len: usize,
+) -> (Vec<ProcessInfo>, Vec<usize>) {
+    let ancestry = Ancestry::new(system);
+    let pids: HashSet<_> = processes.iter().map(|p| p.pid).collect();
+    let mut roots: Vec<ProcessInfo> = vec![];
+    let mut root_index = vec![];
+
+    for p in processes {
+        let root = ancestry.root(system, &pids, p.pid);
+        let ri = match roots.iter().position(|r| r.pid == root) {
+            Some(ri) => ri,
+            None => {
+                let r = processes.iter().find(|p| p.pid == root).unwrap_or(p);
+                roots.push(ProcessInfo {
+                    pid: r.pid,
+                    name: r.name.clone(),
+                    command: r.command.clone(),
+                    exe: r.exe.clone(),
+                    start_time: r.start_time,
+                    values: vec![vec![]; proc_category_len],
+                    valid: true,
+                    ended: None,
+                });
+                roots.len() - 1
+            }
+        };
+        root_index.push(ri);
     }
+
+    (roots, root_index)
 }
 
 #[derive(Default, Clone)]
 pub struct SystemMetrics {
     pub rows: Vec<Vec<f32>>,
+    pub labels: Vec<String>,
 }
 
 impl SystemMetrics {
+    // Name of the row for legends, e.g., `Cpu0` or the adapter of `GpuDevices`
+    pub fn row_label<C: std::fmt::Debug>(&self, category: C, index: usize) -> String {
+        self.labels
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| format!("{:?}{}", category, index))
+    }
+
     pub fn row_avg(&self, index: usize) -> Option<f32> {
         let row = self.rows.get(index)?;
         if row.is_empty() {
@@ -47,6 +337,14 @@ impl SystemMetrics {
         }
     }
 
+    pub fn row_percentile(&self, index: usize, p: f32) -> Option<f32> {
+        percentile(self.rows.get(index)?, p)
+    }
+
+    pub fn row_smoothed(&self, index: usize, window: usize) -> Option<Vec<f32>> {
+        Some(moving_average(self.rows.get(index)?, window))
+    }
+
     pub fn max(&self) -> Option<f32> {
         self.rows
             .iter()
@@ -54,3 +352,59 @@ impl SystemMetrics {
             .max_by(f32::total_cmp)
     }
 }
+
+// Timestamp of `--timestamp`, numbers stay numbers in json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Timestamp {
+    Text(String),
+    Millis(i64),
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text(s) => f.write_str(s),
+            Self::Millis(ms) => write!(f, "{}", ms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_nearest_rank() {
+        assert_eq!(percentile(&[], 50.), None);
+
+        let values = [5., 1., 4., 2., 3.];
+        assert_eq!(percentile(&values, 0.), Some(1.));
+        assert_eq!(percentile(&values, 100.), Some(5.));
+        assert_eq!(percentile(&values, 50.), Some(3.));
+        assert_eq!(percentile(&values, 95.), Some(5.));
+        assert_eq!(percentile(&[7.], 50.), Some(7.));
+    }
+
+    #[test]
+    fn ellipsize() {
+        assert_eq!(ellipsize_middle("precord", 7), "precord");
+        assert_eq!(ellipsize_middle("precord", 0), "");
+        assert_eq!(ellipsize_middle("precord", 1), "…");
+        // The head takes the extra char of an odd split
+        assert_eq!(ellipsize_middle("precord", 4), "pr…d");
+        assert_eq!(ellipsize_middle("precord", 5), "pr…rd");
+        assert_eq!(ellipsize_middle("ßäöüéñ", 4), "ßä…ñ");
+    }
+
+    #[test]
+    fn moving_average_window() {
+        let values = [1., 3., 5., 7.];
+        assert_eq!(moving_average(&values, 2), [1., 2., 4., 6.]);
+        // A window of 0 is taken as 1, the values are unchanged
+        assert_eq!(moving_average(&values, 0), values);
+        // Wider than the input, every value is the mean so far
+        assert_eq!(moving_average(&values, 10), [1., 2., 3., 4.]);
+        assert!(moving_average(&[], 3).is_empty());
+    }
+}
@@ -1,18 +1,46 @@
+use crate::Pid;
 use crossterm::cursor::MoveLeft;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
 use crossterm::style::Print;
 use crossterm::terminal::{Clear, ClearType};
 use crossterm::{execute, terminal};
+use precord_core::Features;
 use regex::Regex;
-use std::fs::OpenOptions;
-use std::io::IsTerminal;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Receiver};
 use std::time::Duration;
 use std::{io, thread};
 
 pub const SWP_EXTENSION: &str = "swp";
 
+// Diagnostics go to stderr, `-v` raises the level from warn, `RUST_LOG` takes precedence
+pub fn init_logger(verbose: u8) {
+    let level = match verbose {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_env("RUST_LOG")
+        .format(|buf, record| {
+            // `\r` keeps lines aligned while the terminal is in raw mode
+            if record.level() <= log::Level::Warn {
+                writeln!(buf, "{}: {}\r", record.level(), record.args())
+            } else {
+                writeln!(buf, "{}\r", record.args())
+            }
+        })
+        .init();
+}
+
+// Output path for streaming one json line per sample to stdout
+pub const STDOUT_PATH: &str = "-";
+
 pub struct CommandPrompt {
     current_command: String,
     rx: Receiver<KeyEvent>,
@@ -20,23 +48,17 @@ pub struct CommandPrompt {
 }
 
 impl CommandPrompt {
+    // Callers check stdin is a terminal, see `main`
     pub fn new() -> Option<Self> {
-        if !io::stdin().is_terminal() {
-            return None;
-        }
-
         terminal::enable_raw_mode().ok()?;
         let (tx, rx) = mpsc::channel();
 
         thread::spawn(move || {
             while let Ok(event) = event::read() {
-                match event {
-                    Event::Key(key_event) => {
-                        if tx.send(key_event).is_err() {
-                            break;
-                        }
+                if let Event::Key(key_event) = event {
+                    if tx.send(key_event).is_err() {
+                        break;
                     }
-                    _ => {}
                 }
             }
         });
@@ -79,7 +101,7 @@ impl CommandPrompt {
                             modifiers: KeyModifiers::CONTROL,
                             ..
                         } => {
-                            break Command::Quit;
+                            break Command::Interrupt;
                         }
                         KeyEvent {
                             code: KeyCode::Char(c),
@@ -93,15 +115,9 @@ impl CommandPrompt {
                             code: KeyCode::Backspace,
                             kind: KeyEventKind::Press,
                             ..
-                        } => {
-                            if self.current_command.pop().is_some() {
-                                execute!(
-                                        &self.stdout,
-                                        MoveLeft(1),
-                                        Clear(ClearType::UntilNewLine),
-                                    )
-                                    .unwrap();
-                            }
+                        } if self.current_command.pop().is_some() => {
+                            execute!(&self.stdout, MoveLeft(1), Clear(ClearType::UntilNewLine),)
+                                .unwrap();
                         }
                         KeyEvent {
                             code: KeyCode::Esc,
@@ -128,7 +144,7 @@ impl CommandPrompt {
                 kind: KeyEventKind::Press,
                 modifiers: KeyModifiers::CONTROL,
                 ..
-            } => Command::Quit,
+            } => Command::Interrupt,
             _ => Command::Continue,
         }
     }
@@ -144,6 +160,8 @@ pub enum Command {
     Timeout,
     Continue,
     Quit,
+    // Ctrl-C, raw mode doesn't raise SIGINT
+    Interrupt,
     Write(Vec<PathBuf>),
     WriteThenQuit(Vec<PathBuf>),
     Time(chrono::Duration),
@@ -166,7 +184,7 @@ impl From<&str> for Command {
             "q" => Self::Quit,
             "w" => {
                 let mut ps = vec![];
-                while let Some(p) = tokens.next() {
+                for p in tokens.by_ref() {
                     let Ok(p) = p.parse::<PathBuf>();
                     ps.push(p);
                 }
@@ -174,7 +192,7 @@ impl From<&str> for Command {
             }
             "wq" => {
                 let mut ps = vec![];
-                while let Some(p) = tokens.next() {
+                for p in tokens.by_ref() {
                     let Ok(p) = p.parse::<PathBuf>();
                     ps.push(p);
                 }
@@ -227,6 +245,40 @@ pub fn extend_path(path_re: &Regex, ps: Vec<PathBuf>) -> Vec<PathBuf> {
         .collect()
 }
 
+// Pids of `--pid-file`, malformed lines are ignored, `None` when the file can't be read
+pub fn read_pid_file(path: &Path) -> Option<HashSet<Pid>> {
+    let content = fs::read_to_string(path).ok()?;
+    Some(
+        content
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect(),
+    )
+}
+
+// Data of a written file reaches the disk, e.g., for backends that write it themselves
+pub fn sync_file(path: &Path) -> io::Result<()> {
+    OpenOptions::new().write(true).open(path)?.sync_all()
+}
+
+// A rename reaches the disk with its directory, NTFS journals renames itself
+pub fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        fs::File::open(parent)?.sync_all()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
 pub fn check_permission(ps: &[PathBuf]) -> bool {
     let mut opt = OpenOptions::new();
     opt.write(true);
@@ -243,9 +295,52 @@ pub fn check_permission(ps: &[PathBuf]) -> bool {
     true
 }
 
+// Why a feature missing from `System::supported_features` produces no data
+pub fn feature_hint(feature: Features) -> &'static str {
+    #[cfg(target_os = "macos")]
+    {
+        if feature == Features::FPS {
+            return "dtrace needs root and SIP allowing it for processes without get-task-allow, \
+                run `csrutil enable --without dtrace` in recovery mode";
+        }
+        if feature.intersects(Features::PROCESS_GPU | Features::CPU_FREQUENCY) {
+            return "powermetrics needs root, re-run with sudo";
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        if feature.intersects(Features::FPS | Features::NET_TRAFFIC) {
+            return "ETW needs Administrator";
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if feature == Features::FPS {
+            return "tracefs with the drm_vblank_event_queued event must be mounted and writable, \
+                e.g., run as root";
+        }
+    }
+
+    let _ = feature;
+    "not supported on this OS"
+}
+
 pub fn adjust_privileges() {
     #[cfg(target_os = "windows")]
     platform_windows::adjust_privileges();
+
+    // Privileges can't be raised in process
+    #[cfg(target_os = "macos")]
+    {
+        let args: Vec<_> = std::env::args().collect();
+        log::error!(
+            "Access denied, re-run with sudo:\n  sudo {}",
+            args.join(" ")
+        );
+        std::process::exit(1);
+    }
 }
 
 #[cfg(target_os = "windows")]
@@ -264,7 +359,7 @@ mod platform_windows {
                 &mut token_handle,
             );
             if !r.as_bool() {
-                eprintln!("OpenProcessToken failed");
+                log::warn!("OpenProcessToken failed");
                 return;
             }
             let token_handle = OwnedHandle::from_raw_handle(token_handle.0 as _);
@@ -273,7 +368,7 @@ mod platform_windows {
             if !Security::LookupPrivilegeValueW(None, SystemServices::SE_DEBUG_NAME, &mut luid)
                 .as_bool()
             {
-                eprintln!("LookupPrivilegeValueW failed");
+                log::warn!("LookupPrivilegeValueW failed");
                 return;
             }
 
@@ -293,7 +388,7 @@ mod platform_windows {
             .as_bool()
                 || Foundation::GetLastError() == Foundation::ERROR_NOT_ALL_ASSIGNED
             {
-                eprintln!("AdjustTokenPrivileges failed");
+                log::warn!("AdjustTokenPrivileges failed");
             }
         }
     }
@@ -304,7 +399,7 @@ mod platform_windows {
 }
 
 pub fn overwrite_detect(ps: &[PathBuf], prompt: &mut CommandPrompt) -> bool {
-    let ps: Vec<_> = ps.into_iter().filter(|p| p.exists()).collect();
+    let ps: Vec<_> = ps.iter().filter(|p| p.exists()).collect();
     if ps.is_empty() {
         return true;
     }
@@ -315,8 +410,5 @@ pub fn overwrite_detect(ps: &[PathBuf], prompt: &mut CommandPrompt) -> bool {
     }
     println!("Do you want to overwrite them?[Y/n](Y)\r");
 
-    match prompt.command(None) {
-        Command::Yes | Command::Empty => true,
-        _ => false,
-    }
+    matches!(prompt.command(None), Command::Yes | Command::Empty)
 }
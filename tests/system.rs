@@ -3,3 +3,32 @@ fn two_smc() {
     let _ = precord_core::System::new(precord_core::Features::SMC, []);
     let _ = precord_core::System::new(precord_core::Features::SMC, []);
 }
+
+#[test]
+fn sampler() {
+    let pid = std::process::id();
+    let sampler = precord_core::System::spawn_sampler(
+        precord_core::Features::PROCESS,
+        [pid],
+        vec![precord_core::ProcessCategory::Cpu],
+        std::time::Duration::from_millis(100),
+    )
+    .unwrap();
+    let samples = sampler.samples().recv().unwrap().unwrap();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].pid, pid);
+}
+
+#[test]
+fn threads_usage() {
+    let pid = std::process::id();
+    let mut system = precord_core::System::new(
+        precord_core::Features::PROCESS | precord_core::Features::THREADS,
+        [pid],
+    )
+    .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    system.update(std::time::Instant::now()).unwrap();
+    let threads = system.process_threads_usage(pid).unwrap();
+    assert!(!threads.is_empty());
+}